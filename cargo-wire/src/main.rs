@@ -0,0 +1,381 @@
+use anyhow::{Result, bail};
+use clap::{Parser, Subcommand};
+use proc_macro2::TokenStream;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use wire_build::{InjectorInfo, ProviderInfo};
+use wire_core::attr::WireAttr;
+use wire_core::codegen;
+use wire_core::graph::{Graph, check_visibility, normalize_type};
+use wire_core::types::display_type;
+
+/// Inspect the wire dependency graph of a crate without compiling it.
+#[derive(Parser)]
+#[command(name = "cargo", bin_name = "cargo")]
+enum Cargo {
+    Wire(Cli),
+}
+
+#[derive(clap::Args)]
+#[command(version, about)]
+struct Cli {
+    /// Source directory to scan.
+    #[arg(long, default_value = "src", global = true)]
+    src: PathBuf,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Validate that every `#[wire]` injector can be resolved.
+    Check {
+        /// Check every crate of every workspace member instead of `--src`.
+        #[arg(long)]
+        workspace: bool,
+    },
+    /// Print the providers an injector calls, in order.
+    Graph {
+        /// Name or path of the injector function.
+        injector: String,
+    },
+    /// Explain which provider supplies a type and who consumes it.
+    Why {
+        /// The type to look up, e.g. `DatabasePool`.
+        ty: String,
+    },
+    /// List providers that no injector reaches.
+    Unused,
+    /// Print the code `#[wire]` generates for an injector (or for all of them).
+    Expand {
+        /// Name or path of the injector function.
+        injector: Option<String>,
+    },
+}
+
+struct Project {
+    providers: Vec<ProviderInfo>,
+    injectors: Vec<InjectorInfo>,
+}
+
+/// `<target dir>/wire` of the workspace in the current directory, wherever it is run from.
+fn cache_dir() -> Result<PathBuf> {
+    let cache_dir = wire_build::workspace::target_dir(Path::new("."))?.join("wire");
+    std::fs::create_dir_all(&cache_dir)?;
+    Ok(cache_dir)
+}
+
+impl Project {
+    fn load(src: &PathBuf) -> Result<Self> {
+        if !src.is_dir() {
+            bail!("source directory {:?} does not exist", src);
+        }
+
+        let scan = wire_build::scan(src, &cache_dir()?.join("scan_cache.json"))?;
+        print_warnings(&scan.warnings);
+        let mut providers: Vec<ProviderInfo> = scan
+            .cache
+            .values()
+            .flat_map(|r| r.providers.clone())
+            .collect();
+        let mut injectors: Vec<InjectorInfo> = scan
+            .cache
+            .values()
+            .flat_map(|r| r.injectors.clone())
+            .collect();
+        providers.sort_by(|a, b| a.path.cmp(&b.path));
+        injectors.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(Project {
+            providers,
+            injectors,
+        })
+    }
+
+    fn find_injector(&self, name: &str) -> Result<&InjectorInfo> {
+        self.injectors
+            .iter()
+            .find(|i| i.path == name || i.path.rsplit("::").next() == Some(name))
+            .ok_or_else(|| {
+                let available: Vec<_> = self.injectors.iter().map(|i| i.path.as_str()).collect();
+                anyhow::anyhow!(
+                    "no #[wire] injector named `{}`. Available injectors: {:?}",
+                    name,
+                    available
+                )
+            })
+    }
+
+    /// Resolves an injector the same way the `#[wire]` macro does, returning the
+    /// providers it calls in order. Fails with the macro's error if it would.
+    fn resolve(&self, injector: &InjectorInfo) -> std::result::Result<Vec<ProviderInfo>, String> {
+        let graph = Graph::new(&self.providers, injector.wrappers.clone())?;
+        let target = normalize_type(&injector.ret, &injector.wrappers);
+        let target = graph.lookup(&target).map_or(target.clone(), str::to_string);
+        let providers = graph.resolve(&target)?;
+        check_visibility(&providers, injector.module())?;
+        // Adapting values to their consumers fails independently of the graph, e.g.
+        // for a `&T` argument fed by an `Option<T>` provider.
+        self.expand(injector)?;
+        Ok(providers)
+    }
+
+    /// Generates the body of an injector, as the `#[wire]` macro does.
+    fn expand(&self, injector: &InjectorInfo) -> std::result::Result<TokenStream, String> {
+        let vis: syn::Visibility = syn::parse_str(&injector.vis).map_err(|e| e.to_string())?;
+        let sig: syn::Signature = syn::parse_str(&injector.sig).map_err(|e| e.to_string())?;
+        let error = injector
+            .error
+            .as_deref()
+            .map(syn::parse_str::<syn::Ident>)
+            .transpose()
+            .map_err(|e| e.to_string())?;
+        codegen::expand(
+            &vis,
+            &sig,
+            &injector.wrappers,
+            error.as_ref(),
+            &self.providers,
+        )
+    }
+}
+
+/// Scan warnings go to stderr, so that the output of `expand` stays valid Rust.
+fn print_warnings(warnings: &[String]) {
+    for warning in warnings {
+        eprintln!("warning: {}", warning);
+    }
+}
+
+fn main() -> ExitCode {
+    let Cargo::Wire(cli) = Cargo::parse();
+    match run(cli) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("error: {:#}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<bool> {
+    if let Command::Check { workspace: true } = cli.command {
+        return check_workspace();
+    }
+
+    let project = Project::load(&cli.src)?;
+    match cli.command {
+        Command::Check { .. } => check(&project),
+        Command::Graph { injector } => graph(&project, &injector),
+        Command::Why { ty } => why(&project, &ty),
+        Command::Unused => unused(&project),
+        Command::Expand { injector } => expand(&project, injector.as_deref()),
+    }
+}
+
+fn check(project: &Project) -> Result<bool> {
+    if project.injectors.is_empty() {
+        let wrappers = WireAttr::default().wrappers;
+        if let Err(e) = Graph::new(&project.providers, wrappers) {
+            eprintln!("error: {}", e);
+            return Ok(false);
+        }
+        println!(
+            "no #[wire] injectors found ({} providers)",
+            project.providers.len()
+        );
+        return Ok(true);
+    }
+
+    let mut ok = true;
+    for injector in &project.injectors {
+        match project.resolve(injector) {
+            Ok(providers) => println!("ok: {} ({} providers)", injector.path, providers.len()),
+            Err(e) => {
+                ok = false;
+                eprintln!("error: {}: {}", injector.path, e);
+            }
+        }
+    }
+    Ok(ok)
+}
+
+fn check_workspace() -> Result<bool> {
+    let manifests =
+        wire_build::workspace::scan_workspace(Path::new("."), &cache_dir()?.join("workspace"))?;
+    let mut ok = true;
+    for manifest in manifests {
+        if manifest.injectors.is_empty() {
+            continue;
+        }
+        println!(
+            "{} ({} `{}`):",
+            manifest.package, manifest.kind, manifest.target
+        );
+        print_warnings(&manifest.warnings);
+        let project = Project {
+            providers: manifest.providers,
+            injectors: manifest.injectors,
+        };
+        ok &= check(&project)?;
+    }
+    Ok(ok)
+}
+
+fn graph(project: &Project, name: &str) -> Result<bool> {
+    let injector = project.find_injector(name)?;
+    let providers = match project.resolve(injector) {
+        Ok(providers) => providers,
+        Err(e) => bail!("{}: {}", injector.path, e),
+    };
+
+    println!("{} -> {}", injector.path, display_type(&injector.ret));
+    for (i, p) in providers.iter().enumerate() {
+        let args: Vec<String> = p.args.iter().map(|a| display_type(&a.ty)).collect();
+        let fallible = if p.is_result { "?" } else { "" };
+        println!(
+            "  {}. {}({}){} -> {}",
+            i + 1,
+            p.path,
+            args.join(", "),
+            fallible,
+            display_type(&p.ret)
+        );
+    }
+    Ok(true)
+}
+
+fn why(project: &Project, ty: &str) -> Result<bool> {
+    let wrappers = WireAttr::default().wrappers;
+    let graph = Graph::new(&project.providers, wrappers.clone()).map_err(anyhow::Error::msg)?;
+    let normalized = normalize_type(ty, &wrappers);
+    let Some(key) = graph.lookup(&normalized) else {
+        let mut available: Vec<_> = graph.nodes.keys().cloned().collect();
+        available.sort();
+        bail!(
+            "no provider for type `{}`. Available types: {:?}",
+            ty,
+            available
+        );
+    };
+    let provider = &graph.nodes[key].provider;
+
+    println!("{} is provided by {}", ty, provider.path);
+    if normalize_type(&provider.ret, &wrappers) != key {
+        println!("  bound from {}", display_type(&provider.ret));
+    }
+    if provider.is_result {
+        println!("  the provider returns a Result");
+    }
+    if provider.is_option {
+        println!("  the provider returns an Option and may provide nothing");
+    }
+
+    if !provider.args.is_empty() {
+        println!("  depends on:");
+        for arg in &provider.args {
+            let lookup_ty = normalize_type(&arg.lookup_type(), &wrappers);
+            let source = graph
+                .lookup(&lookup_ty)
+                .map_or("<missing provider>".to_string(), |k| {
+                    graph.nodes[k].provider.path.clone()
+                });
+            let inject = arg.from.as_ref().map_or(String::new(), |f| {
+                format!(" #[inject({})]", display_type(f))
+            });
+            println!(
+                "    {}: {}{} <- {}",
+                arg.name,
+                display_type(&arg.ty),
+                inject,
+                source
+            );
+        }
+    }
+
+    let mut consumers = Vec::new();
+    for p in &project.providers {
+        for arg in &p.args {
+            let lookup_ty = normalize_type(&arg.lookup_type(), &wrappers);
+            if graph.lookup(&lookup_ty) == Some(key) {
+                consumers.push(format!(
+                    "{} (argument `{}: {}`)",
+                    p.path,
+                    arg.name,
+                    display_type(&arg.ty)
+                ));
+            }
+        }
+    }
+    for injector in &project.injectors {
+        let target = normalize_type(&injector.ret, &injector.wrappers);
+        if graph.lookup(&target) == Some(key) {
+            consumers.push(format!("{} (injector target)", injector.path));
+        }
+    }
+
+    if consumers.is_empty() {
+        println!("  not consumed by any provider or injector");
+    } else {
+        println!("  consumed by:");
+        for consumer in consumers {
+            println!("    {}", consumer);
+        }
+    }
+    Ok(true)
+}
+
+fn unused(project: &Project) -> Result<bool> {
+    let mut used = HashSet::new();
+    for injector in &project.injectors {
+        let Ok(graph) = Graph::new(&project.providers, injector.wrappers.clone()) else {
+            continue;
+        };
+        let target = normalize_type(&injector.ret, &injector.wrappers);
+        for key in graph.reachable(&target) {
+            if let Some(node) = graph.nodes.get(&key) {
+                used.insert(node.provider.path.clone());
+            }
+        }
+    }
+
+    let unused: Vec<_> = project
+        .providers
+        .iter()
+        .filter(|p| !used.contains(&p.path))
+        .collect();
+    if unused.is_empty() {
+        println!(
+            "all {} providers are reachable from an injector",
+            project.providers.len()
+        );
+    }
+    for p in unused {
+        println!("{} -> {}", p.path, display_type(&p.ret));
+    }
+    Ok(true)
+}
+
+fn expand(project: &Project, name: Option<&str>) -> Result<bool> {
+    let injectors = match name {
+        Some(name) => vec![project.find_injector(name)?],
+        None => project.injectors.iter().collect(),
+    };
+
+    let mut ok = true;
+    for injector in injectors {
+        match project.expand(injector) {
+            Ok(tokens) => {
+                println!("// {}", injector.path);
+                print!("{}", codegen::pretty(&tokens));
+            }
+            Err(e) => {
+                ok = false;
+                eprintln!("error: {}: {}", injector.path, e);
+            }
+        }
+    }
+    Ok(ok)
+}
@@ -3,10 +3,9 @@ mod db;
 mod repo;
 mod services;
 
-use wire::{provider, wire};
-use std::error::Error;
-use std::sync::Arc;
 use crate::repo::Repository;
+use std::error::Error;
+use wire::{provider, wire};
 
 // The `wire` macro will generate the body of this function.
 // It will now handle Result-returning providers automatically.
@@ -18,7 +17,10 @@ fn main() -> Result<(), Box<dyn Error>> {
     println!("Successfully initialized App!");
     println!("User Service Pool: {:?}", app.user_service.pool);
     // This should print "Data from Mock Database (Secondary)" because of the override
-    println!("User Service Repo Data: {}", app.user_service.repo.get_data());
+    println!(
+        "User Service Repo Data: {}",
+        app.user_service.repo.get_data()
+    );
     Ok(())
 }
 
@@ -1,4 +1,3 @@
-
 pub trait Repository: Send + Sync + std::fmt::Debug {
     fn get_data(&self) -> String;
 }
@@ -20,4 +19,3 @@ impl Repository for MockRepository {
         "Data from Mock Database (Secondary)".to_string()
     }
 }
-
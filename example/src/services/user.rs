@@ -9,16 +9,21 @@ pub struct UserService {
     pub repo: Arc<dyn Repository>,
 }
 
-#[provider]
-pub fn provide_user_service(
-    pool: &Arc<DatabasePool>, 
-    // Injecting a specific implementation directly on the parameter
-    #[inject(std::sync::Arc<repo::MockRepository>)]
-    repo: &Arc<dyn Repository>,
-) -> UserService {
-    println!("UserService: Accessing targeted repo -> {}", repo.get_data());
-    UserService { 
-        pool: pool.clone(),
-        repo: repo.clone(),
+impl UserService {
+    // Associated functions work as providers too; `Self` resolves to `UserService`.
+    #[provider]
+    pub fn new(
+        pool: &Arc<DatabasePool>,
+        // Injecting a specific implementation directly on the parameter
+        #[inject(std::sync::Arc<repo::MockRepository>)] repo: &Arc<dyn Repository>,
+    ) -> Self {
+        println!(
+            "UserService: Accessing targeted repo -> {}",
+            repo.get_data()
+        );
+        UserService {
+            pool: pool.clone(),
+            repo: repo.clone(),
+        }
     }
 }
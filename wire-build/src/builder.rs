@@ -0,0 +1,341 @@
+//! Configurable entry point for build scripts, in the style of `prost-build` and
+//! `tonic-build`.
+
+use crate::scanner::ScanOptions;
+use crate::{
+    CfgEnv, GraphFormat, InjectorInfo, ManifestFormat, PRODUCER, ProviderInfo, ProviderManifest,
+    Scan, check_visibility, dependency_providers, rerun_if_changed, scan_modules, write_graphs,
+    write_if_changed,
+};
+use anyhow::{Context, Result, bail};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use wire_core::codegen;
+
+/// How much `wire-build` validates on its own instead of leaving it to `#[wire]`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Strictness {
+    /// Only fail on problems rustc would report less clearly, such as inaccessible
+    /// providers. Everything else is reported by the `#[wire]` macro.
+    #[default]
+    Lenient,
+    /// Additionally resolve every injector at build time, so that missing,
+    /// conflicting or circular providers fail the build script.
+    Strict,
+}
+
+/// Configures and runs the provider scanner.
+///
+/// ```ignore
+/// // build.rs
+/// wire_build::Builder::new()
+///     .src("src")
+///     .exclude("src/generated/**")
+///     .strictness(wire_build::Strictness::Strict)
+///     .generate(out_dir.join("providers.bin"))?;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Builder {
+    roots: Vec<PathBuf>,
+    include: Vec<String>,
+    exclude: Vec<String>,
+    provider_attributes: Vec<String>,
+    cache_path: Option<PathBuf>,
+    format: Option<ManifestFormat>,
+    graph_formats: Vec<GraphFormat>,
+    strictness: Strictness,
+    warnings_as_errors: bool,
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a crate to scan: either a source directory containing `lib.rs` and/or
+    /// `main.rs`, or the root file of a crate such as `src/bin/tool.rs`. Defaults
+    /// to `src` if no root is added.
+    pub fn src(mut self, root: impl Into<PathBuf>) -> Self {
+        self.roots.push(root.into());
+        self
+    }
+
+    /// Only scans module files matching one of the include globs, e.g.
+    /// `src/services/**`. Crate roots are always scanned.
+    pub fn include(mut self, pattern: impl Into<String>) -> Self {
+        self.include.push(pattern.into());
+        self
+    }
+
+    /// Skips module files (and the modules below them) matching the glob.
+    pub fn exclude(mut self, pattern: impl Into<String>) -> Self {
+        self.exclude.push(pattern.into());
+        self
+    }
+
+    /// Replaces the full paths of the attributes that mark providers, `wire::provider`
+    /// by default. Attributes are resolved through the `use` items of their module, so
+    /// `use wire::provider as p;` followed by `#[p]` matches `wire::provider`.
+    pub fn provider_attributes<I, S>(mut self, paths: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.provider_attributes = paths.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Where to keep the scan cache, `<output stem>_cache.json` next to the output by default.
+    pub fn cache_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.cache_path = Some(path.into());
+        self
+    }
+
+    /// Encoding of the manifest written by [`Builder::generate`], derived from the
+    /// output file extension by default (see [`ManifestFormat::for_path`]).
+    pub fn format(mut self, format: ManifestFormat) -> Self {
+        self.format = Some(format);
+        self
+    }
+
+    /// Also writes the dependency graphs in these formats, see
+    /// [`generate_with_graphs`](crate::generate_with_graphs).
+    pub fn graphs(mut self, formats: &[GraphFormat]) -> Self {
+        self.graph_formats = formats.to_vec();
+        self
+    }
+
+    pub fn strictness(mut self, strictness: Strictness) -> Self {
+        self.strictness = strictness;
+        self
+    }
+
+    /// Fails instead of skipping files, modules or items the scanner cannot handle.
+    pub fn warnings_as_errors(mut self, enabled: bool) -> Self {
+        self.warnings_as_errors = enabled;
+        self
+    }
+
+    /// Scans the configured crates and writes the provider manifest to `output_path`,
+    /// see [`generate`](crate::generate).
+    pub fn generate(&self, output_path: impl AsRef<Path>) -> Result<()> {
+        let output_path = output_path.as_ref();
+        let cache_path = self.cache_path_for(output_path, "providers");
+
+        eprintln!(
+            "wire-build: generating providers from {:?} to {:?}",
+            self.roots(),
+            output_path
+        );
+        let (providers, injectors) = self.scan_crate(&cache_path)?;
+
+        if !self.graph_formats.is_empty() {
+            let out_dir = output_path
+                .parent()
+                .expect("output_path must have a parent");
+            write_graphs(
+                &providers,
+                &injectors,
+                &out_dir.join("wire_graph"),
+                &self.graph_formats,
+            )?;
+        }
+
+        let format = self
+            .format
+            .unwrap_or_else(|| ManifestFormat::for_path(output_path));
+        let manifest = ProviderManifest {
+            injectors,
+            ..ProviderManifest::new(PRODUCER, providers)
+        };
+        write_if_changed(output_path, &manifest.to_bytes(format))?;
+
+        Ok(())
+    }
+
+    /// Resolves every injector and writes the generated functions to `output_path`,
+    /// see [`generate_injectors`](crate::generate_injectors).
+    pub fn generate_injectors(&self, output_path: impl AsRef<Path>) -> Result<()> {
+        let output_path = output_path.as_ref();
+        let cache_path = self.cache_path_for(output_path, "wire_gen");
+
+        eprintln!(
+            "wire-build: generating injectors from {:?} to {:?}",
+            self.roots(),
+            output_path
+        );
+        let (providers, injectors) = self.scan_crate(&cache_path)?;
+        check_single_module(&injectors)?;
+
+        let mut items = Vec::new();
+        for injector in &injectors {
+            let vis: syn::Visibility = syn::parse_str(&injector.vis)?;
+            let sig: syn::Signature = syn::parse_str(&injector.sig)?;
+            let error = injector
+                .error
+                .as_deref()
+                .map(syn::parse_str::<syn::Ident>)
+                .transpose()?;
+            let expanded =
+                codegen::expand(&vis, &sig, &injector.wrappers, error.as_ref(), &providers)
+                    .map_err(|e| {
+                        anyhow::anyhow!("failed to generate injector {}: {}", injector.path, e)
+                    })?;
+            items.push(expanded);
+        }
+
+        let code = codegen::pretty(&quote::quote! { #(#items)* });
+        write_if_changed(
+            output_path,
+            format!("// @generated by wire-build. Do not edit.\n\n{}", code).as_bytes(),
+        )?;
+
+        // Injector stubs are gated behind `#[cfg(wire)]`, which is never set.
+        println!("cargo::rustc-check-cfg=cfg(wire)");
+
+        Ok(())
+    }
+
+    /// Scans the configured crates without writing anything but the cache. Warnings
+    /// are returned in [`Scan::warnings`], or fail the scan with `warnings_as_errors`.
+    pub fn scan(&self, cache_path: &Path) -> Result<Scan> {
+        let mut roots = Vec::new();
+        for root in self.roots() {
+            roots.extend(crate_roots(&root)?);
+        }
+
+        let mut options = ScanOptions::new(CfgEnv::from_env());
+        if !self.provider_attributes.is_empty() {
+            options.provider_attributes = self.provider_attributes.clone();
+        }
+        options.include = compile_globs(&self.include)?;
+        options.exclude = compile_globs(&self.exclude)?;
+
+        let scan = scan_modules(&roots, &options, cache_path)?;
+        if self.warnings_as_errors && !scan.warnings.is_empty() {
+            bail!(
+                "wire-build warnings treated as errors:\n{}",
+                scan.warnings.join("\n")
+            );
+        }
+        Ok(scan)
+    }
+
+    /// Scans, merges dependency providers and validates the injectors as configured.
+    /// Providers are sorted so that the manifest is stable across runs.
+    fn scan_crate(&self, cache_path: &Path) -> Result<(Vec<ProviderInfo>, Vec<InjectorInfo>)> {
+        let scan = self.scan(cache_path)?;
+        scan.print_warnings();
+        rerun_if_changed(&scan.cache, cache_path);
+
+        let mut providers: Vec<ProviderInfo> = scan
+            .cache
+            .values()
+            .flat_map(|r| r.providers.clone())
+            .collect();
+        let mut injectors: Vec<InjectorInfo> = scan
+            .cache
+            .values()
+            .flat_map(|r| r.injectors.clone())
+            .collect();
+        providers.sort_by(|a, b| a.path.cmp(&b.path));
+        injectors.sort_by(|a, b| a.path.cmp(&b.path));
+        providers.extend(dependency_providers()?);
+
+        check_visibility(&providers, &injectors)?;
+        if self.strictness == Strictness::Strict {
+            let mut errors = Vec::new();
+            for injector in &injectors {
+                let vis: syn::Visibility = syn::parse_str(&injector.vis)?;
+                let sig: syn::Signature = syn::parse_str(&injector.sig)?;
+                let error = injector
+                    .error
+                    .as_deref()
+                    .map(syn::parse_str::<syn::Ident>)
+                    .transpose()?;
+                if let Err(e) =
+                    codegen::expand(&vis, &sig, &injector.wrappers, error.as_ref(), &providers)
+                {
+                    errors.push(format!("injector {}: {}", injector.path, e));
+                }
+            }
+            if !errors.is_empty() {
+                bail!("{}", errors.join("\n"));
+            }
+        }
+
+        Ok((providers, injectors))
+    }
+
+    fn roots(&self) -> Vec<PathBuf> {
+        if self.roots.is_empty() {
+            vec![PathBuf::from("src")]
+        } else {
+            self.roots.clone()
+        }
+    }
+
+    fn cache_path_for(&self, output_path: &Path, default_stem: &str) -> PathBuf {
+        self.cache_path.clone().unwrap_or_else(|| {
+            let out_dir = output_path
+                .parent()
+                .expect("output_path must have a parent");
+            let file_stem = output_path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or(default_stem);
+            out_dir.join(format!("{}_cache.json", file_stem))
+        })
+    }
+}
+
+/// The crate root files for a root given to [`Builder::src`].
+pub(crate) fn crate_roots(root: &Path) -> Result<Vec<PathBuf>> {
+    if root.is_file() {
+        return Ok(vec![root.to_path_buf()]);
+    }
+    let roots: Vec<PathBuf> = ["lib.rs", "main.rs"]
+        .iter()
+        .map(|file| root.join(file))
+        .filter(|file| file.exists())
+        .collect();
+    if roots.is_empty() {
+        bail!(
+            "no lib.rs or main.rs found in {:?}; pass the crate root file instead",
+            root
+        );
+    }
+    Ok(roots)
+}
+
+/// Fails if the injectors are defined in more than one module: the generated file is
+/// included in a single module, where the types of other modules' signatures may not
+/// resolve and callers would not find the injectors.
+fn check_single_module(injectors: &[InjectorInfo]) -> Result<()> {
+    let mut modules: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for injector in injectors {
+        modules
+            .entry(injector.module())
+            .or_default()
+            .push(&injector.path);
+    }
+    if modules.len() > 1 {
+        let listed: Vec<String> = modules
+            .iter()
+            .map(|(module, paths)| format!("{} ({})", module, paths.join(", ")))
+            .collect();
+        bail!(
+            "generate_injectors writes a single file that is included in one module, but injectors are defined in {}; \
+             move them into one module",
+            listed.join(", ")
+        );
+    }
+    Ok(())
+}
+
+fn compile_globs(patterns: &[String]) -> Result<Vec<glob::Pattern>> {
+    patterns
+        .iter()
+        .map(|p| glob::Pattern::new(p).with_context(|| format!("invalid glob pattern `{}`", p)))
+        .collect()
+}
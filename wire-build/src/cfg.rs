@@ -0,0 +1,264 @@
+//! Evaluation of `#[cfg(...)]` predicates against the build script environment.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::hash::{Hash, Hasher};
+use syn::punctuated::Punctuated;
+use syn::{Attribute, Meta, Token};
+
+/// The cfg options that are active for the crate being built.
+///
+/// Cargo passes them to build scripts as `CARGO_FEATURE_<NAME>` and
+/// `CARGO_CFG_<KEY>` environment variables. The `wire` cfg is always considered
+/// set, since the scanner is what `#[cfg(wire)]` injector stubs are written for.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct CfgEnv {
+    features: BTreeSet<String>,
+    /// Values of key-value options such as `target_os`; flags such as `unix` have no values.
+    options: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl CfgEnv {
+    /// Reads the cfg options from the build script environment, falling back to
+    /// [`CfgEnv::host`] when not running inside a build script.
+    pub fn from_env() -> Self {
+        if env::var_os("CARGO_CFG_TARGET_OS").is_none() {
+            return Self::host();
+        }
+
+        Self::from_vars(env::vars())
+    }
+
+    /// Reads the cfg options from build script environment variables.
+    fn from_vars(vars: impl IntoIterator<Item = (String, String)>) -> Self {
+        let mut cfg = CfgEnv::default();
+        for (key, value) in vars {
+            if let Some(feature) = key.strip_prefix("CARGO_FEATURE_") {
+                cfg.features.insert(feature.to_string());
+            } else if let Some(option) = key.strip_prefix("CARGO_CFG_") {
+                let values = value
+                    .split(',')
+                    .filter(|v| !v.is_empty())
+                    .map(str::to_string)
+                    .collect();
+                cfg.options.insert(option.to_lowercase(), values);
+            }
+        }
+        cfg
+    }
+
+    /// The options of the platform this code was compiled for, without any features.
+    /// Used by tools that scan sources outside of a build script.
+    pub fn host() -> Self {
+        let mut cfg = CfgEnv::default();
+        let mut set = |key: &str, value: Option<&str>| {
+            cfg.options
+                .entry(key.to_string())
+                .or_default()
+                .extend(value.map(str::to_string));
+        };
+        set("target_os", Some(env::consts::OS));
+        set("target_family", Some(env::consts::FAMILY));
+        set("target_arch", Some(env::consts::ARCH));
+        if cfg!(unix) {
+            set("unix", None);
+        }
+        if cfg!(windows) {
+            set("windows", None);
+        }
+        if cfg!(debug_assertions) {
+            set("debug_assertions", None);
+        }
+        cfg
+    }
+
+    /// A stable-within-a-build hash used to invalidate cached scan results when
+    /// the active options change.
+    pub fn fingerprint(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Whether every `#[cfg(...)]` attribute in `attrs` is satisfied.
+    pub fn is_enabled(&self, attrs: &[Attribute]) -> bool {
+        attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("cfg"))
+            .all(|attr| {
+                match attr.parse_args::<Meta>() {
+                    Ok(predicate) => self.eval(&predicate),
+                    // Leave malformed predicates to rustc.
+                    Err(_) => true,
+                }
+            })
+    }
+
+    /// Evaluates a cfg predicate such as `all(unix, feature = "postgres")`.
+    pub fn eval(&self, predicate: &Meta) -> bool {
+        match predicate {
+            Meta::Path(path) => {
+                let Some(name) = path.get_ident().map(|i| i.to_string()) else {
+                    return false;
+                };
+                name == "wire" || self.options.contains_key(&name)
+            }
+            Meta::NameValue(nv) => {
+                let Some(name) = nv.path.get_ident().map(|i| i.to_string()) else {
+                    return false;
+                };
+                let syn::Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Str(value),
+                    ..
+                }) = &nv.value
+                else {
+                    return false;
+                };
+                if name == "feature" {
+                    self.features.contains(&feature_env_name(&value.value()))
+                } else {
+                    self.options
+                        .get(&name)
+                        .is_some_and(|values| values.contains(&value.value()))
+                }
+            }
+            Meta::List(list) => {
+                let Ok(nested) =
+                    list.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)
+                else {
+                    return false;
+                };
+                if list.path.is_ident("all") {
+                    nested.iter().all(|p| self.eval(p))
+                } else if list.path.is_ident("any") {
+                    nested.iter().any(|p| self.eval(p))
+                } else if list.path.is_ident("not") {
+                    nested.len() == 1 && !self.eval(&nested[0])
+                } else {
+                    false
+                }
+            }
+        }
+    }
+}
+
+/// Cargo exposes feature `foo-bar` as `CARGO_FEATURE_FOO_BAR`.
+fn feature_env_name(feature: &str) -> String {
+    feature.to_uppercase().replace('-', "_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The environment of a build script for `target_os = "linux"` with the given features.
+    fn linux(features: &[&str]) -> CfgEnv {
+        let mut vars = vec![
+            ("CARGO_CFG_TARGET_OS".to_string(), "linux".to_string()),
+            ("CARGO_CFG_TARGET_FAMILY".to_string(), "unix".to_string()),
+            ("CARGO_CFG_UNIX".to_string(), String::new()),
+            (
+                "CARGO_CFG_TARGET_FEATURE".to_string(),
+                "fxsr,sse,sse2".to_string(),
+            ),
+            ("CARGO_PKG_NAME".to_string(), "app".to_string()),
+        ];
+        vars.extend(
+            features
+                .iter()
+                .map(|f| (format!("CARGO_FEATURE_{}", f), "1".to_string())),
+        );
+        CfgEnv::from_vars(vars)
+    }
+
+    fn eval(cfg: &CfgEnv, predicate: &str) -> bool {
+        cfg.eval(&syn::parse_str(predicate).unwrap())
+    }
+
+    #[test]
+    fn flags_and_key_value_options() {
+        let cfg = linux(&[]);
+        assert!(eval(&cfg, "unix"));
+        assert!(!eval(&cfg, "windows"));
+        assert!(eval(&cfg, r#"target_os = "linux""#));
+        assert!(!eval(&cfg, r#"target_os = "macos""#));
+        assert!(eval(&cfg, r#"target_feature = "sse2""#));
+        assert!(!eval(&cfg, r#"target_feature = "avx""#));
+        assert!(!eval(&cfg, "pkg_name"));
+    }
+
+    #[test]
+    fn wire_is_always_set() {
+        assert!(eval(&CfgEnv::default(), "wire"));
+        assert!(eval(&linux(&[]), "all(wire, unix)"));
+    }
+
+    #[test]
+    fn features_map_to_cargo_feature_variables() {
+        let cfg = linux(&["POSTGRES", "TLS_NATIVE"]);
+        assert!(eval(&cfg, r#"feature = "postgres""#));
+        assert!(eval(&cfg, r#"feature = "tls-native""#));
+        assert!(eval(&cfg, r#"feature = "tls_native""#));
+        assert!(!eval(&cfg, r#"feature = "mysql""#));
+    }
+
+    #[test]
+    fn all_any_and_not() {
+        let cfg = linux(&["POSTGRES"]);
+        assert!(eval(&cfg, r#"all(unix, feature = "postgres")"#));
+        assert!(!eval(&cfg, r#"all(unix, feature = "mysql")"#));
+        assert!(eval(&cfg, "all()"));
+        assert!(eval(&cfg, r#"any(windows, feature = "postgres")"#));
+        assert!(!eval(&cfg, "any()"));
+        assert!(eval(&cfg, "not(windows)"));
+        assert!(!eval(&cfg, "not(unix)"));
+        assert!(eval(
+            &cfg,
+            r#"all(not(windows), any(target_os = "macos", target_os = "linux"))"#
+        ));
+    }
+
+    #[test]
+    fn malformed_predicates_are_false() {
+        let cfg = linux(&[]);
+        assert!(!eval(&cfg, "not(unix, windows)"));
+        assert!(!eval(&cfg, "not()"));
+        assert!(!eval(&cfg, "one_of(unix)"));
+        assert!(!eval(&cfg, "target::os"));
+        assert!(!eval(&cfg, "target_os = linux"));
+    }
+
+    #[test]
+    fn every_cfg_attribute_must_hold() {
+        let cfg = linux(&["POSTGRES"]);
+        let attrs = |item: &str| syn::parse_str::<syn::ItemFn>(item).unwrap().attrs;
+        assert!(cfg.is_enabled(&attrs("fn f() {}")));
+        assert!(cfg.is_enabled(&attrs(
+            "#[cfg(unix)] #[cfg(feature = \"postgres\")] #[inline] fn f() {}"
+        )));
+        assert!(!cfg.is_enabled(&attrs("#[cfg(unix)] #[cfg(windows)] fn f() {}")));
+        // Left to rustc to report.
+        assert!(cfg.is_enabled(&attrs("#[cfg(unix,,)] fn f() {}")));
+    }
+
+    #[test]
+    fn fingerprint_changes_with_the_options() {
+        assert_eq!(
+            linux(&["A", "B"]).fingerprint(),
+            linux(&["B", "A"]).fingerprint()
+        );
+        assert_ne!(
+            linux(&["A"]).fingerprint(),
+            linux(&["A", "B"]).fingerprint()
+        );
+        assert_ne!(linux(&[]).fingerprint(), CfgEnv::default().fingerprint());
+    }
+
+    #[test]
+    fn host_has_no_features() {
+        let cfg = CfgEnv::host();
+        assert!(cfg.features.is_empty());
+        assert!(eval(&cfg, &format!("target_os = {:?}", env::consts::OS)));
+    }
+}
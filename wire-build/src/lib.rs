@@ -1,226 +1,494 @@
-use anyhow::Result;
-use quote::ToTokens;
+use crate::scanner::ScanOptions;
+use anyhow::{Result, bail};
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::env;
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::time::SystemTime;
-use syn::{File, FnArg, Item, Pat};
+use wire_core::attr::WireAttr;
+use wire_core::export::GraphExport;
+use wire_core::graph::{Graph, normalize_type};
+use wire_core::models::PUBLIC;
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct ProviderArgument {
-    pub name: String,
-    pub ty: String,
-    pub from: Option<String>,
-}
+mod builder;
+mod cfg;
+mod scanner;
+pub mod workspace;
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct ProviderInfo {
-    pub path: String,
-    pub args: Vec<ProviderArgument>,
-    pub ret: String,
-    pub is_result: bool,
-    pub bindings: Vec<String>,
-}
+pub use builder::{Builder, Strictness};
+pub use cfg::CfgEnv;
+pub use scanner::{ModuleFile, ModuleLookup};
+pub use wire_core::export::GraphFormat;
+pub use wire_core::manifest::{FORMAT_VERSION, ManifestFormat, ProviderManifest};
+pub use wire_core::models::{InjectorInfo, ProviderArgument, ProviderInfo};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct FileScanResult {
-    pub mtime: SystemTime,
+    /// [`content_hash`] of the file when it was scanned.
+    pub hash: u64,
+    /// Version of `wire-build` that scanned the file.
+    pub version: String,
+    /// [`CfgEnv::fingerprint`] of the options the file was scanned with.
+    pub cfg: u64,
+    pub module: ModuleFile,
     pub providers: Vec<ProviderInfo>,
+    pub injectors: Vec<InjectorInfo>,
+    pub children: Vec<ModuleFile>,
+    /// How `children` were found, see [`ModuleLookup`].
+    pub lookups: Vec<ModuleLookup>,
+    /// Effective visibility of the structs, enums, unions and type aliases defined
+    /// in the file, by path, see [`ProviderInfo::vis`].
+    pub types: BTreeMap<String, String>,
+    /// Problems that made the scanner skip parts of the file.
+    pub warnings: Vec<String>,
+    /// Malformed wire attributes, which fail the scan until they are fixed.
+    pub errors: Vec<String>,
 }
 
 pub type ScanCache = HashMap<PathBuf, FileScanResult>;
 
-/// Scans the source directory and generates a JSON file containing all found providers.
+/// The result of scanning one or more crates.
+#[derive(Debug, Clone)]
+pub struct Scan {
+    pub cache: ScanCache,
+    /// Warnings of all scanned files, including ones whose results came from the cache.
+    pub warnings: Vec<String>,
+}
+
+impl Scan {
+    /// Reports the warnings to cargo, which shows them in the build output.
+    /// Build script stderr is only shown when the build fails, so this is for
+    /// build scripts only; other tools should print [`Scan::warnings`] themselves.
+    pub fn print_warnings(&self) {
+        for warning in &self.warnings {
+            println!("cargo:warning=wire-build: {}", warning);
+        }
+    }
+}
+
+/// 64-bit FNV-1a hash of a file's contents, used to detect changes independently of
+/// modification times, which are unreliable after `git checkout` or restoring CI caches.
+pub fn content_hash(content: &[u8]) -> u64 {
+    content.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Version recorded in [`FileScanResult::version`]; cached results of other versions are discarded.
+const SCANNER_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// [`ProviderManifest::producer`] of the manifests written by this crate.
+const PRODUCER: &str = concat!("wire-build ", env!("CARGO_PKG_VERSION"));
+
+/// Scans the source directory and writes a manifest containing all found providers.
 /// This is intended to be called from a build script.
+///
+/// The manifest is written as JSON if `output_path` ends in `.json` and in the
+/// compact binary format otherwise (see [`ManifestFormat::for_path`]); `#[wire]`
+/// reads either.
+///
+/// Use [`Builder`] for more options.
 pub fn generate(src_dir: impl AsRef<Path>, output_path: impl AsRef<Path>) -> Result<()> {
-    let src_dir = src_dir.as_ref();
-    let output_path = output_path.as_ref();
-    let out_dir = output_path.parent().expect("output_path must have a parent");
-    let file_stem = output_path.file_stem().and_then(|s| s.to_str()).unwrap_or("providers");
-    let cache_path = out_dir.join(format!("{}_cache.json", file_stem));
-
-    eprintln!("wire-build: generating providers from {:?} to {:?}", src_dir, output_path);
-    let cache = scan(src_dir, &cache_path)?;
-    let all_providers: Vec<ProviderInfo> = cache
-        .values()
-        .flat_map(|result| result.providers.clone())
+    Builder::new().src(src_dir.as_ref()).generate(output_path)
+}
+
+/// Like [`generate`], but additionally writes the dependency graph in each of the
+/// given `formats` to `wire_graph/` next to `output_path`: one file for the whole
+/// crate (`crate.<ext>`) and one per `#[wire]` injector, named after its path with
+/// `::` replaced by `__` (e.g. `crate__app__init.<ext>`).
+pub fn generate_with_graphs(
+    src_dir: impl AsRef<Path>,
+    output_path: impl AsRef<Path>,
+    formats: &[GraphFormat],
+) -> Result<()> {
+    Builder::new()
+        .src(src_dir.as_ref())
+        .graphs(formats)
+        .generate(output_path)
+}
+
+/// Fails if an injector would call a provider that is not visible from its module,
+/// which rustc would otherwise report as a private function at the `#[wire]` call site.
+/// Injectors that cannot be resolved are left to the `#[wire]` macro to report.
+pub fn check_visibility(providers: &[ProviderInfo], injectors: &[InjectorInfo]) -> Result<()> {
+    let mut errors = Vec::new();
+    for injector in injectors {
+        let Ok(graph) = Graph::new(providers, injector.wrappers.clone()) else {
+            continue;
+        };
+        let target = normalize_type(&injector.ret, &injector.wrappers);
+        let target = graph.lookup(&target).map_or(target.clone(), str::to_string);
+        let Ok(resolved) = graph.resolve(&target) else {
+            continue;
+        };
+        if let Err(e) = wire_core::graph::check_visibility(&resolved, injector.module()) {
+            errors.push(format!("injector {}: {}", injector.path, e));
+        }
+    }
+    if !errors.is_empty() {
+        anyhow::bail!("{}", errors.join("\n"));
+    }
+    Ok(())
+}
+
+/// Metadata key under which [`export_providers`] publishes the exported manifest.
+/// Cargo passes it to dependent build scripts as `DEP_<LINKS>_WIRE_PROVIDERS`.
+const EXPORT_METADATA_KEY: &str = "wire_providers";
+
+/// Publishes this crate's providers to the build scripts of crates that depend on it.
+///
+/// Reads the manifest written by [`generate`], rewrites every `crate::...`
+/// path (of the providers, their types, bindings and `map_err`) to
+/// `::<crate_name>::...` and writes the result to `wire_exports.bin` next to
+/// it. Cargo only forwards build script metadata for packages with a `links` key,
+/// so the exporting crate needs e.g. `links = "my_infra"` in its `[package]` section.
+/// Only providers that are reachable from other crates are exported, and providers
+/// merged from dependencies are not re-exported.
+pub fn export_providers(providers_path: impl AsRef<Path>) -> Result<()> {
+    let providers_path = providers_path.as_ref();
+    if env::var_os("CARGO_MANIFEST_LINKS").is_none() {
+        anyhow::bail!(
+            "wire_build::export_providers requires a `links` key in the [package] section of Cargo.toml"
+        );
+    }
+    let crate_name = env::var("CARGO_PKG_NAME")?.replace('-', "_");
+
+    let manifest = read_manifest(providers_path)?;
+    let exported: Vec<ProviderInfo> = manifest
+        .providers
+        .into_iter()
+        .filter(|p| p.vis == PUBLIC)
+        .filter_map(|p| p.exported_as(&crate_name))
         .collect();
 
-    fs::write(output_path, serde_json::to_string_pretty(&all_providers)?)?;
+    let export_path = providers_path.with_file_name("wire_exports.bin");
+    write_if_changed(
+        &export_path,
+        &ProviderManifest::new(PRODUCER, exported).to_bytes(ManifestFormat::Binary),
+    )?;
+
+    println!("cargo:{}={}", EXPORT_METADATA_KEY, export_path.display());
 
     Ok(())
 }
 
-/// Scans a source directory for provider functions, using a cache for incremental processing.
-pub fn scan(src_dir: &Path, cache_path: &Path) -> Result<ScanCache> {
-    let mut cache: ScanCache = if cache_path.exists() {
-        let cache_content = fs::read(cache_path)?;
-        serde_json::from_slice(&cache_content).unwrap_or_default()
-    } else {
-        HashMap::new()
-    };
+/// Reads a providers manifest written by this or another version of `wire-build`.
+pub fn read_manifest(path: &Path) -> Result<ProviderManifest> {
+    let content =
+        fs::read(path).map_err(|e| anyhow::anyhow!("failed to read {:?}: {}", path, e))?;
+    ProviderManifest::from_bytes(&content).map_err(|e| anyhow::anyhow!("{:?}: {}", path, e))
+}
 
-    let pattern = format!("{}/**/*.rs", src_dir.to_str().unwrap());
-    let mut seen_files = HashSet::new();
+/// Loads the providers exported by direct dependencies through [`export_providers`].
+pub fn dependency_providers() -> Result<Vec<ProviderInfo>> {
+    let suffix = format!("_{}", EXPORT_METADATA_KEY.to_uppercase());
+    let mut manifests: Vec<(String, String)> = env::vars()
+        .filter(|(key, _)| key.starts_with("DEP_") && key.ends_with(&suffix))
+        .collect();
+    manifests.sort();
 
-    for entry in glob::glob(&pattern)? {
-        let path = entry?;
-        seen_files.insert(path.clone());
-        let mtime = fs::metadata(&path)?.modified()?;
+    let mut providers = Vec::new();
+    for (key, path) in manifests {
+        let manifest = read_manifest(Path::new(&path))
+            .map_err(|e| anyhow::anyhow!("failed to load providers exported via {}: {}", key, e))?;
+        println!("cargo:rerun-if-changed={}", path);
+        providers.extend(manifest.providers);
+    }
+    Ok(providers)
+}
 
-        if let Some(cached_result) = cache.get(&path) {
-            if cached_result.mtime == mtime {
-                continue;
-            }
-        }
+/// Writes the crate-wide graph and one graph per injector into `graph_dir`.
+/// Graphs that cannot be built (e.g. because of conflicting providers) are
+/// skipped with a warning, the `#[wire]` macro reports the actual error.
+pub fn write_graphs(
+    providers: &[ProviderInfo],
+    injectors: &[InjectorInfo],
+    graph_dir: &Path,
+    formats: &[GraphFormat],
+) -> Result<()> {
+    fs::create_dir_all(graph_dir)?;
+
+    let default_wrappers = WireAttr::default().wrappers;
+    let mut exports = Vec::new();
+    match Graph::new(providers, default_wrappers.clone()) {
+        Ok(graph) => exports.push(GraphExport::new(&graph, "crate", None, &default_wrappers)),
+        Err(e) => eprintln!("wire-build: Warning: Skipping crate graph export: {}", e),
+    }
 
-        eprintln!("wire-build: Scanning file: {:?}", &path);
-        let content = fs::read_to_string(&path)?;
-        let ast = match syn::parse_file(&content) {
-            Ok(ast) => ast,
+    for injector in injectors {
+        let graph = match Graph::new(providers, injector.wrappers.clone()) {
+            Ok(graph) => graph,
             Err(e) => {
-                eprintln!("wire-build: Warning: Skipping file {:?} due to syntax error: {}", path, e);
-                cache.remove(&path); // Ensure stale data is removed from cache
+                eprintln!(
+                    "wire-build: Warning: Skipping graph export for {}: {}",
+                    injector.path, e
+                );
                 continue;
             }
         };
+        let target = normalize_type(&injector.ret, &injector.wrappers);
+        exports.push(GraphExport::new(
+            &graph,
+            &injector.path,
+            Some(&target),
+            &injector.wrappers,
+        ));
+    }
 
-        // Construct module path from file path
-        let mod_path = path_to_module_path(&path, src_dir);
+    for export in &exports {
+        // The full path, since injectors in different modules may share a name.
+        let file_name = export.name.replace("::", "__");
+        for format in formats {
+            let path = graph_dir.join(format!("{}.{}", file_name, format.extension()));
+            fs::write(path, export.render(*format))?;
+        }
+    }
+
+    Ok(())
+}
 
-        let providers = parse_providers_from_ast(&ast, &mod_path)?;
+/// Resolves every `#[wire]` injector at build time and writes the generated functions
+/// as plain Rust source to `output_path` (e.g. `OUT_DIR/wire_gen.rs`), so that the
+/// `wire` proc-macro is not needed to compile the crate.
+///
+/// Injectors are declared as stubs that are never compiled, and the generated file
+/// is included in the same module. All injectors must be defined in that one module:
+///
+/// ```ignore
+/// #[cfg(wire)]
+/// #[wire]
+/// pub fn initialize_app() -> Result<App, Box<dyn Error>> {}
+///
+/// include!(concat!(env!("OUT_DIR"), "/wire_gen.rs"));
+/// ```
+///
+/// Providers can be marked with `#[cfg_attr(wire, provider)]` (and parameters with
+/// `#[cfg_attr(wire, inject(...))]`), which the scanner treats like the plain attributes.
+pub fn generate_injectors(src_dir: impl AsRef<Path>, output_path: impl AsRef<Path>) -> Result<()> {
+    Builder::new()
+        .src(src_dir.as_ref())
+        .generate_injectors(output_path)
+}
+
+enum FileScan {
+    /// The cached result is still valid.
+    Cached,
+    Scanned(Box<FileScanResult>),
+    Unreadable(String),
+}
 
-        let result = FileScanResult { mtime, providers };
-        cache.insert(path, result);
+fn scan_file(
+    module: ModuleFile,
+    cache: &ScanCache,
+    options: &ScanOptions,
+    fingerprint: u64,
+) -> FileScan {
+    let path = &module.path;
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => {
+            return FileScan::Unreadable(format!("Skipping module file {:?}: {}", path, e));
+        }
+    };
+    let hash = content_hash(content.as_bytes());
+
+    if let Some(cached_result) = cache.get(path)
+        && cached_result.hash == hash
+        && cached_result.module == module
+        && cached_result.cfg == fingerprint
+        && cached_result.lookups.iter().all(ModuleLookup::is_current)
+    {
+        return FileScan::Cached;
     }
 
-    // Remove files that no longer exist from cache
-    cache.retain(|path, _| seen_files.contains(path));
+    let parsed = if !scanner::may_contain_items(&content, options) {
+        scanner::ParsedModule::default()
+    } else {
+        eprintln!("wire-build: Scanning file: {:?}", path);
+        match syn::parse_file(&content) {
+            Ok(ast) => scanner::parse_module(&ast, &module, options),
+            Err(e) => {
+                // Keep an empty entry so that the file is still watched and rescanned once fixed.
+                let mut parsed = scanner::ParsedModule::default();
+                parsed.warnings.push(format!(
+                    "Skipping file {:?} due to syntax error: {}",
+                    path, e
+                ));
+                parsed
+            }
+        }
+    };
 
-    fs::write(cache_path, serde_json::to_string_pretty(&cache)?)?;
+    FileScan::Scanned(Box::new(FileScanResult {
+        hash,
+        version: SCANNER_VERSION.to_string(),
+        cfg: fingerprint,
+        module,
+        providers: parsed.providers,
+        injectors: parsed.injectors,
+        children: parsed.children,
+        lookups: parsed.lookups,
+        types: parsed.types,
+        warnings: parsed.warnings,
+        errors: parsed.errors,
+    }))
+}
 
-    Ok(cache)
+/// Tells cargo to re-run the build script when one of the scanned files or the
+/// scan cache changes. This replaces cargo's default of re-running on any change
+/// in the package, and also covers module files outside `src` (e.g. via `#[path]`).
+///
+/// Module files that are declared but do not exist are watched through the nearest
+/// directory that does, since cargo re-runs build scripts on every build for paths
+/// that are missing. Moving a module file is noticed as its old path going missing.
+pub(crate) fn rerun_if_changed(cache: &ScanCache, cache_path: &Path) {
+    let mut paths: BTreeSet<PathBuf> = cache.keys().cloned().collect();
+    for result in cache.values() {
+        let unresolved = result
+            .lookups
+            .iter()
+            .filter(|lookup| lookup.found.is_none())
+            .flat_map(|lookup| lookup.candidates.first());
+        let missing = result
+            .children
+            .iter()
+            .map(|child| &child.path)
+            .filter(|path| !path.exists());
+        for path in unresolved.chain(missing) {
+            if let Some(dir) = path.ancestors().skip(1).find(|dir| dir.is_dir()) {
+                paths.insert(dir.to_path_buf());
+            }
+        }
+    }
+    for path in paths {
+        println!("cargo:rerun-if-changed={}", path.display());
+    }
+    println!("cargo:rerun-if-changed={}", cache_path.display());
 }
 
-fn path_to_module_path(path: &Path, src_dir: &Path) -> String {
-    path.strip_prefix(src_dir)
-        .unwrap()
-        .with_extension("")
-        .to_str()
-        .unwrap()
-        .replace("/", "::")
-        .replace("\\", "::")
+/// Writes `content` to `path` unless the file already has exactly that content,
+/// so that its modification time only changes when it does and crates that
+/// depend on it are not rebuilt needlessly. Returns whether the file was written.
+pub fn write_if_changed(path: &Path, content: &[u8]) -> std::io::Result<bool> {
+    if fs::read(path).is_ok_and(|existing| existing == content) {
+        return Ok(false);
+    }
+    fs::write(path, content)?;
+    Ok(true)
 }
 
-/// Parses a syn::File AST to find functions with the `#[provider]` attribute.
-fn parse_providers_from_ast(ast: &File, mod_path: &str) -> Result<Vec<ProviderInfo>> {
-    let mut providers = Vec::new();
+/// Scans a source directory for provider functions, using a cache for incremental processing.
+///
+/// The module tree is walked from `lib.rs` and `main.rs` in `src_dir`, following
+/// `mod` declarations, inline `mod` blocks and `#[path]` attributes. `#[cfg(...)]`
+/// on items and modules is evaluated against the build script's features and
+/// target (see [`CfgEnv::from_env`]). Warnings are returned rather than printed.
+pub fn scan(src_dir: &Path, cache_path: &Path) -> Result<Scan> {
+    let roots = builder::crate_roots(src_dir)?;
+    scan_modules(&roots, &ScanOptions::new(CfgEnv::from_env()), cache_path)
+}
 
-    for item in &ast.items {
-        if let Item::Fn(func) = item {
-            let is_provider = func.attrs.iter().any(|attr| {
-                attr.path()
-                    .segments
-                    .last()
-                    .map_or(false, |segment| segment.ident == "provider")
-            });
-
-            if is_provider {
-                let fn_name = func.sig.ident.to_string();
-                let path = if mod_path == "main" || mod_path == "lib" {
-                    format!("crate::{}", fn_name)
-                } else if mod_path.ends_with("::mod") {
-                    let base_mod = mod_path.strip_suffix("::mod").unwrap();
-                    format!("crate::{}::{}", base_mod, fn_name)
-                } else {
-                    format!("crate::{}::{}", mod_path, fn_name)
-                };
-
-                let args = func
-                    .sig
-                    .inputs
-                    .iter()
-                    .filter_map(|arg| {
-                        if let FnArg::Typed(pat_type) = arg {
-                            let name = if let Pat::Ident(pat_ident) = &*pat_type.pat {
-                                pat_ident.ident.to_string()
-                            } else {
-                                "_".to_string()
-                            };
-                            let ty = pat_type.ty.to_token_stream().to_string();
-                            let from = pat_type.attrs.iter().find_map(|attr| {
-                                if attr.path().is_ident("inject") {
-                                    if let Ok(ty) = attr.parse_args::<syn::Type>() {
-                                        return Some(ty.to_token_stream().to_string());
-                                    }
-                                }
-                                if attr.path().is_ident("wire") {
-                                    if let Ok(list) = attr.meta.require_list() {
-                                        if let Ok(nested) = list.parse_args_with(syn::punctuated::Punctuated::<syn::Meta, syn::Token![,]>::parse_terminated) {
-                                            for meta in nested {
-                                                if let syn::Meta::NameValue(nv) = meta {
-                                                    if nv.path.is_ident("from") {
-                                                        if let syn::Expr::Lit(expr_lit) = &nv.value {
-                                                            if let syn::Lit::Str(lit) = &expr_lit.lit {
-                                                                return Some(lit.value());
-                                                            }
-                                                        }
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                                None
-                            });
-                            Some(ProviderArgument { name, ty, from })
-                        } else {
-                            None
-                        }
-                    })
-                    .collect();
-
-                let (ret, is_result) = if let syn::ReturnType::Type(_, ty) = &func.sig.output {
-                    let ty_str = ty.to_token_stream().to_string();
-                    
-                    // Simple check for Result patterns
-                    if let syn::Type::Path(type_path) = &**ty {
-                        let last = type_path.path.segments.last().unwrap();
-                        if last.ident == "Result" {
-                            // Extract T from Result<T, E> or anyhow::Result<T>
-                            if let syn::PathArguments::AngleBracketed(args) = &last.arguments {
-                                if let Some(syn::GenericArgument::Type(inner_ty)) = args.args.first() {
-                                    (inner_ty.to_token_stream().to_string(), true)
-                                } else {
-                                    (ty_str, true) // Assume Result but couldn't unwrap
-                                }
-                            } else {
-                                (ty_str, true)
-                            }
-                        } else {
-                            (ty_str, false)
-                        }
-                    } else {
-                        (ty_str, false)
-                    }
-                } else {
-                    ("()".to_string(), false)
-                };
-
-                let bindings = func.attrs.iter().filter_map(|attr| {
-                    if attr.path().segments.last().map_or(false, |s| s.ident == "bind") {
-                        if let Ok(nested) = attr.parse_args::<syn::Type>() {
-                             return Some(nested.to_token_stream().to_string());
-                        }
-                    }
-                    None
-                }).collect();
-
-                providers.push(ProviderInfo { path, args, ret, is_result, bindings });
+/// Scans the crate whose root file is `root`, e.g. `src/lib.rs`, `src/bin/tool.rs`
+/// or `examples/demo.rs`.
+pub fn scan_target(root: &Path, cache_path: &Path) -> Result<Scan> {
+    scan_modules(
+        &[root.to_path_buf()],
+        &ScanOptions::new(CfgEnv::from_env()),
+        cache_path,
+    )
+}
+
+/// Items and modules gated by `#[cfg(...)]` are only scanned when `options.cfg` enables them.
+/// Fails if any scanned file has malformed wire attributes.
+fn scan_modules(roots: &[PathBuf], options: &ScanOptions, cache_path: &Path) -> Result<Scan> {
+    let fingerprint = options.fingerprint();
+    let mut cache: ScanCache = if cache_path.exists() {
+        let cache_content = fs::read(cache_path)?;
+        let mut cache: ScanCache = serde_json::from_slice(&cache_content).unwrap_or_default();
+        // Results of other scanner versions may be missing fields or follow other rules.
+        cache.retain(|_, result| result.version == SCANNER_VERSION);
+        cache
+    } else {
+        HashMap::new()
+    };
+
+    // The module tree is walked breadth-first, scanning each level in parallel.
+    let mut seen_files = HashSet::new();
+    let mut warnings = Vec::new();
+    let mut frontier: Vec<ModuleFile> = roots.iter().cloned().map(ModuleFile::root).collect();
+
+    while !frontier.is_empty() {
+        frontier.retain(|module| {
+            (module.mod_path.is_empty() || options.is_included(&module.path))
+                && seen_files.insert(module.path.clone())
+        });
+        let outcomes: Vec<(PathBuf, FileScan)> = frontier
+            .par_drain(..)
+            .map(|module| {
+                (
+                    module.path.clone(),
+                    scan_file(module, &cache, options, fingerprint),
+                )
+            })
+            .collect();
+
+        for (path, outcome) in outcomes {
+            match outcome {
+                FileScan::Cached => frontier.extend(cache[&path].children.iter().cloned()),
+                FileScan::Scanned(result) => {
+                    frontier.extend(result.children.iter().cloned());
+                    cache.insert(path, *result);
+                }
+                FileScan::Unreadable(warning) => {
+                    warnings.push(warning);
+                    cache.remove(&path);
+                }
             }
         }
     }
 
-    Ok(providers)
+    // Remove files that are no longer part of the module tree from cache
+    cache.retain(|path, _| seen_files.contains(path));
+
+    // Sorted, so that an unchanged cache serializes identically and is not rewritten.
+    let sorted: BTreeMap<&PathBuf, &FileScanResult> = cache.iter().collect();
+    write_if_changed(
+        cache_path,
+        serde_json::to_string_pretty(&sorted)?.as_bytes(),
+    )?;
+
+    // Checked after writing the cache, so that only the files that changed are rescanned once fixed.
+    let errors: Vec<&str> = sorted
+        .values()
+        .flat_map(|result| result.errors.iter().map(String::as_str))
+        .collect();
+    if !errors.is_empty() {
+        bail!("invalid wire attributes:\n{}", errors.join("\n"));
+    }
+
+    warnings.extend(
+        sorted
+            .values()
+            .flat_map(|result| result.warnings.iter().cloned()),
+    );
+    restrict_to_impl_types(&mut cache);
+    Ok(Scan { cache, warnings })
+}
+
+/// Restricts the visibility of providers in `impl` blocks to that of their type,
+/// when it is defined in the crate. This is done after writing the cache, as the
+/// type may be defined in another file than the `impl` block.
+fn restrict_to_impl_types(cache: &mut ScanCache) {
+    let types: HashMap<String, String> = cache
+        .values()
+        .flat_map(|result| result.types.clone())
+        .collect();
+    for provider in cache.values_mut().flat_map(|result| &mut result.providers) {
+        if let Some((type_path, _)) = provider.path.rsplit_once("::")
+            && let Some(type_vis) = types.get(type_path)
+        {
+            provider.vis = scanner::narrowest_vis(&provider.vis, type_vis).to_string();
+        }
+    }
 }
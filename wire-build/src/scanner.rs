@@ -0,0 +1,1333 @@
+//! Walks a crate's module tree and extracts providers and injectors from each file.
+
+use crate::cfg::CfgEnv;
+use crate::{InjectorInfo, ProviderArgument, ProviderInfo};
+use quote::ToTokens;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+use syn::visit_mut::VisitMut;
+use syn::{File, FnArg, Item, ItemFn, ItemImpl, Signature, Type, UseTree};
+use wire_core::attr::{ProviderAttr, WireAttr, parse_bind_attr, parse_inject_attr};
+use wire_core::models::PUBLIC;
+use wire_core::types::{argument_name, split_output};
+
+/// A source file in the crate's module tree.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ModuleFile {
+    pub path: PathBuf,
+    /// Module path relative to the crate root, empty for the root itself.
+    pub mod_path: String,
+    /// Whether child modules live next to the file (crate roots, `mod.rs` and
+    /// `#[path]` files) rather than in a directory named after it.
+    pub mod_rs: bool,
+    /// Where items of the module can be reached from, in the format of
+    /// [`ProviderInfo::vis`]. Crate roots are public.
+    pub vis: String,
+}
+
+impl ModuleFile {
+    pub fn root(path: PathBuf) -> Self {
+        ModuleFile {
+            path,
+            mod_path: String::new(),
+            mod_rs: true,
+            vis: PUBLIC.to_string(),
+        }
+    }
+}
+
+/// The files a `mod name;` declaration may refer to, and the one that was found.
+///
+/// Which file a module is in depends on the files that exist, so the result of
+/// scanning the declaring file is only valid as long as the lookup finds the same one.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ModuleLookup {
+    /// `name.rs` and `name/mod.rs`, in the order they are tried.
+    pub candidates: Vec<PathBuf>,
+    pub found: Option<PathBuf>,
+}
+
+impl ModuleLookup {
+    fn new(candidates: Vec<PathBuf>) -> Self {
+        let found = candidates.iter().find(|path| path.exists()).cloned();
+        ModuleLookup { candidates, found }
+    }
+
+    /// Whether the lookup would still find the same file.
+    pub fn is_current(&self) -> bool {
+        self.candidates.iter().find(|path| path.exists()) == self.found.as_ref()
+    }
+}
+
+/// Path of the `#[provider]` attribute, accepted unless configured otherwise.
+pub(crate) const PROVIDER_ATTRIBUTE: &str = "wire::provider";
+/// Path of the `#[wire]` attribute that marks injectors.
+const INJECTOR_ATTRIBUTE: &str = "wire::wire";
+
+/// What the scanner looks for, beyond the module tree itself.
+#[derive(Debug, Clone)]
+pub(crate) struct ScanOptions {
+    pub cfg: CfgEnv,
+    /// Full paths of the attributes that mark providers, e.g. `wire::provider`.
+    pub provider_attributes: Vec<String>,
+    /// Module files to scan (all if empty) and to skip. Crate roots are always scanned.
+    pub include: Vec<glob::Pattern>,
+    pub exclude: Vec<glob::Pattern>,
+}
+
+impl ScanOptions {
+    pub fn new(cfg: CfgEnv) -> Self {
+        ScanOptions {
+            cfg,
+            provider_attributes: vec![PROVIDER_ATTRIBUTE.to_string()],
+            include: Vec::new(),
+            exclude: Vec::new(),
+        }
+    }
+
+    /// Whether a module file below the crate root is part of the scan.
+    pub fn is_included(&self, path: &Path) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|p| p.matches_path(path)))
+            && !self.exclude.iter().any(|p| p.matches_path(path))
+    }
+
+    /// Changes whenever the options would produce different results for the same file.
+    /// Include and exclude patterns only decide which files are scanned.
+    pub fn fingerprint(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.cfg.fingerprint().hash(&mut hasher);
+        self.provider_attributes.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+/// Everything found in a single file, including its inline `mod` blocks.
+#[derive(Default)]
+pub(crate) struct ParsedModule {
+    pub providers: Vec<ProviderInfo>,
+    pub injectors: Vec<InjectorInfo>,
+    /// Files declared with `mod name;` that belong to the module tree.
+    pub children: Vec<ModuleFile>,
+    /// How the files of `mod name;` declarations without `#[path]` were found.
+    pub lookups: Vec<ModuleLookup>,
+    /// Effective visibility of the types defined in the file, by path.
+    pub types: BTreeMap<String, String>,
+    pub warnings: Vec<String>,
+    /// Malformed wire attributes, as `file:line:column: message`.
+    pub errors: Vec<String>,
+}
+
+impl ParsedModule {
+    fn push_error(&mut self, file: &Path, error: syn::Error) {
+        for error in error {
+            let start = error.span().start();
+            self.errors.push(format!(
+                "{}:{}:{}: {}",
+                file.display(),
+                start.line,
+                start.column + 1,
+                error
+            ));
+        }
+    }
+}
+
+/// Cheap check that skips the full parse of files that cannot declare providers,
+/// injectors, child modules or types, whose visibility limits that of the providers
+/// in their `impl` blocks.
+pub(crate) fn may_contain_items(content: &str, options: &ScanOptions) -> bool {
+    ["wire", "mod", "struct", "enum", "union", "type"]
+        .iter()
+        .any(|needle| content.contains(needle))
+        || options
+            .provider_attributes
+            .iter()
+            .any(|path| content.contains(path.rsplit("::").next().unwrap_or(path)))
+}
+
+pub(crate) fn parse_module(ast: &File, module: &ModuleFile, options: &ScanOptions) -> ParsedModule {
+    let file_dir = module.path.parent().unwrap_or(Path::new("."));
+    let children_dir = if module.mod_rs {
+        file_dir.to_path_buf()
+    } else {
+        let stem = module.path.file_stem().unwrap_or_default();
+        file_dir.join(stem)
+    };
+
+    let mut parsed = ParsedModule::default();
+    let scope = Scope {
+        options,
+        file: &module.path,
+        mod_path: &module.mod_path,
+        vis: &module.vis,
+        children_dir: &children_dir,
+        path_base: file_dir,
+    };
+    walk_items(&ast.items, &scope, &mut parsed);
+    parsed
+}
+
+/// Where in the module tree a list of items lives.
+///
+/// `children_dir` is where `mod name;` looks for `name.rs` or `name/mod.rs`, and
+/// `path_base` is what `#[path = "..."]` is relative to. Both follow the rules of
+/// the Rust reference: at the top level of a file, `#[path]` is relative to the
+/// file's directory, inside inline modules it is relative to `children_dir`.
+struct Scope<'a> {
+    options: &'a ScanOptions,
+    /// The file the items are in, for error locations.
+    file: &'a Path,
+    mod_path: &'a str,
+    /// Effective visibility of the module, see [`ModuleFile::vis`].
+    vis: &'a str,
+    children_dir: &'a Path,
+    path_base: &'a Path,
+}
+
+/// Collects providers and injectors from `items`, descending into inline modules.
+/// Items and modules whose `#[cfg(...)]` is not satisfied are skipped.
+fn walk_items(items: &[Item], scope: &Scope, parsed: &mut ParsedModule) {
+    let cfg = &scope.options.cfg;
+    let mod_path = scope.mod_path;
+    let imports = Imports::collect(items);
+    for item in items {
+        match item {
+            Item::Fn(func) if cfg.is_enabled(&func.attrs) => {
+                let path = item_path(mod_path, &func.sig.ident.to_string());
+                let vis = effective_vis(scope.vis, &func.vis, mod_path);
+                match parse_provider(
+                    &func.attrs,
+                    &func.sig,
+                    path.clone(),
+                    vis,
+                    None,
+                    scope,
+                    &imports,
+                ) {
+                    Ok(Some(provider)) => parsed.providers.push(provider),
+                    Ok(None) => warn_unresolved_provider_attrs(
+                        &effective_attrs(&func.attrs, cfg),
+                        &path,
+                        scope.options,
+                        &imports,
+                        &mut parsed.warnings,
+                    ),
+                    Err(e) => parsed.push_error(scope.file, e),
+                }
+                if let Some(injector) =
+                    parse_injector(func, mod_path, cfg, &imports, &mut parsed.warnings)
+                {
+                    parsed.injectors.push(injector);
+                }
+            }
+            Item::Impl(item_impl) if cfg.is_enabled(&item_impl.attrs) => {
+                parse_impl_providers(item_impl, scope, &imports, parsed);
+            }
+            Item::Struct(syn::ItemStruct {
+                attrs, vis, ident, ..
+            })
+            | Item::Enum(syn::ItemEnum {
+                attrs, vis, ident, ..
+            })
+            | Item::Union(syn::ItemUnion {
+                attrs, vis, ident, ..
+            })
+            | Item::Type(syn::ItemType {
+                attrs, vis, ident, ..
+            }) if cfg.is_enabled(attrs) => {
+                parsed.types.insert(
+                    item_path(mod_path, &ident.to_string()),
+                    effective_vis(scope.vis, vis, mod_path),
+                );
+            }
+            Item::Mod(item_mod) if cfg.is_enabled(&item_mod.attrs) => {
+                let name = item_mod.ident.to_string();
+                let child_mod_path = if mod_path.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{}::{}", mod_path, name)
+                };
+                let path_attr = path_attr(&effective_attrs(&item_mod.attrs, cfg));
+                let vis = effective_vis(scope.vis, &item_mod.vis, mod_path);
+
+                if let Some((_, items)) = &item_mod.content {
+                    let dir = scope
+                        .children_dir
+                        .join(path_attr.as_deref().unwrap_or(&name));
+                    let inner = Scope {
+                        options: scope.options,
+                        file: scope.file,
+                        mod_path: &child_mod_path,
+                        vis: &vis,
+                        children_dir: &dir,
+                        path_base: &dir,
+                    };
+                    walk_items(items, &inner, parsed);
+                } else if let Some(path) = path_attr {
+                    parsed.children.push(ModuleFile {
+                        path: scope.path_base.join(path),
+                        mod_path: child_mod_path,
+                        mod_rs: true,
+                        vis,
+                    });
+                } else {
+                    let file = scope.children_dir.join(format!("{}.rs", name));
+                    let mod_rs_file = scope.children_dir.join(&name).join("mod.rs");
+                    let lookup = ModuleLookup::new(vec![file.clone(), mod_rs_file.clone()]);
+                    match &lookup.found {
+                        Some(path) => parsed.children.push(ModuleFile {
+                            path: path.clone(),
+                            mod_path: child_mod_path,
+                            mod_rs: *path == mod_rs_file,
+                            vis,
+                        }),
+                        None => parsed.warnings.push(format!(
+                            "No file found for module `{}` (looked for {:?} and {:?})",
+                            child_mod_path, file, mod_rs_file
+                        )),
+                    }
+                    parsed.lookups.push(lookup);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Reads `#[path = "..."]`.
+fn path_attr(attrs: &[syn::Attribute]) -> Option<String> {
+    attrs.iter().find_map(|attr| {
+        let syn::Meta::NameValue(nv) = &attr.meta else {
+            return None;
+        };
+        if !nv.path.is_ident("path") {
+            return None;
+        }
+        match &nv.value {
+            syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Str(lit),
+                ..
+            }) => Some(lit.value()),
+            _ => None,
+        }
+    })
+}
+
+/// Builds the absolute path of an item defined at the top level of the module `mod_path`.
+fn item_path(mod_path: &str, name: &str) -> String {
+    if mod_path.is_empty() {
+        format!("crate::{}", name)
+    } else {
+        format!("crate::{}::{}", mod_path, name)
+    }
+}
+
+/// Returns the attributes as seen by the compiler for the active cfg options:
+/// `#[cfg_attr(predicate, ...)]` is unwrapped when the predicate holds. Since the
+/// `wire` cfg is always set for the scanner, code generated with
+/// [`generate_injectors`](crate::generate_injectors) can mark providers with
+/// `#[cfg_attr(wire, provider)]` without depending on the `wire` crate at compile time.
+fn effective_attrs(attrs: &[syn::Attribute], cfg: &CfgEnv) -> Vec<syn::Attribute> {
+    let mut effective = Vec::new();
+    for attr in attrs {
+        if attr.path().is_ident("cfg_attr")
+            && let Ok(nested) = attr.parse_args_with(
+                syn::punctuated::Punctuated::<syn::Meta, syn::Token![,]>::parse_terminated,
+            )
+        {
+            let mut nested = nested.into_iter();
+            if nested.next().is_some_and(|predicate| cfg.eval(&predicate)) {
+                effective.extend(nested.map(|meta| syn::parse_quote!(#[#meta])));
+            }
+            continue;
+        }
+        effective.push(attr.clone());
+    }
+    effective
+}
+
+/// Names brought into scope by the `use` and `extern crate` items of one module,
+/// mapped to the paths they refer to.
+#[derive(Default)]
+struct Imports {
+    names: HashMap<String, String>,
+    /// Names of the items defined in the module itself.
+    items: HashSet<String>,
+}
+
+impl Imports {
+    fn collect(items: &[Item]) -> Self {
+        let mut imports = Imports::default();
+        for item in items {
+            match item {
+                Item::Use(item_use) => {
+                    let prefix = if item_use.leading_colon.is_some() {
+                        "::"
+                    } else {
+                        ""
+                    };
+                    imports.add_tree(prefix, &item_use.tree);
+                }
+                Item::ExternCrate(extern_crate) => {
+                    if let Some((_, rename)) = &extern_crate.rename {
+                        imports
+                            .names
+                            .insert(rename.to_string(), extern_crate.ident.to_string());
+                    }
+                }
+                Item::Fn(ItemFn {
+                    sig: Signature { ident, .. },
+                    ..
+                })
+                | Item::Struct(syn::ItemStruct { ident, .. })
+                | Item::Enum(syn::ItemEnum { ident, .. })
+                | Item::Union(syn::ItemUnion { ident, .. })
+                | Item::Type(syn::ItemType { ident, .. })
+                | Item::Const(syn::ItemConst { ident, .. })
+                | Item::Static(syn::ItemStatic { ident, .. })
+                | Item::Trait(syn::ItemTrait { ident, .. })
+                | Item::Mod(syn::ItemMod { ident, .. }) => {
+                    imports.items.insert(ident.to_string());
+                }
+                _ => {}
+            }
+        }
+        imports
+    }
+
+    fn add_tree(&mut self, prefix: &str, tree: &UseTree) {
+        let join = |ident: &syn::Ident| {
+            if prefix.is_empty() || prefix == "::" {
+                format!("{}{}", prefix, ident)
+            } else {
+                format!("{}::{}", prefix, ident)
+            }
+        };
+        match tree {
+            UseTree::Path(path) => self.add_tree(&join(&path.ident), &path.tree),
+            UseTree::Name(name) if name.ident == "self" => {
+                let name = prefix.rsplit("::").next().unwrap_or(prefix).to_string();
+                self.names.insert(name, prefix.to_string());
+            }
+            UseTree::Name(name) => {
+                self.names.insert(name.ident.to_string(), join(&name.ident));
+            }
+            UseTree::Rename(rename) => {
+                let path = if rename.ident == "self" {
+                    prefix.to_string()
+                } else {
+                    join(&rename.ident)
+                };
+                self.names.insert(rename.rename.to_string(), path);
+            }
+            UseTree::Group(group) => group
+                .items
+                .iter()
+                .for_each(|tree| self.add_tree(prefix, tree)),
+            UseTree::Glob(_) => {}
+        }
+    }
+
+    /// The full path of an attribute, with its first segment resolved through the imports.
+    ///
+    /// A single-segment attribute that was not imported by name can only be in scope
+    /// through a glob import, or be visible to the scanner alone (under `#[cfg(wire)]`
+    /// or `#[cfg_attr(wire, ...)]`), so it is assumed to come from `wire`.
+    fn resolve(&self, path: &syn::Path) -> String {
+        let segments: Vec<String> = path.segments.iter().map(|s| s.ident.to_string()).collect();
+        if path.leading_colon.is_some() {
+            return segments.join("::");
+        }
+        match self.names.get(&segments[0]) {
+            Some(full) => std::iter::once(full.trim_start_matches("::").to_string())
+                .chain(segments[1..].iter().cloned())
+                .collect::<Vec<_>>()
+                .join("::"),
+            None if segments.len() == 1 => format!("wire::{}", segments[0]),
+            None => segments.join("::"),
+        }
+    }
+}
+
+fn is_provider(attrs: &[syn::Attribute], options: &ScanOptions, imports: &Imports) -> bool {
+    attrs.iter().any(|attr| {
+        options
+            .provider_attributes
+            .contains(&imports.resolve(attr.path()))
+    })
+}
+
+/// Warns about attributes of a function that is not a provider whose name is that
+/// of a provider attribute, e.g. `provider` imported from a module that re-exports
+/// `wire::provider`. The scanner does not follow re-exports, so the function would
+/// otherwise be missing from the graph without explanation.
+fn warn_unresolved_provider_attrs(
+    attrs: &[syn::Attribute],
+    item: &str,
+    options: &ScanOptions,
+    imports: &Imports,
+    warnings: &mut Vec<String>,
+) {
+    let last_segment = |path: &str| path.rsplit("::").next().unwrap_or_default().to_string();
+    for attr in attrs {
+        let resolved = imports.resolve(attr.path());
+        if options
+            .provider_attributes
+            .iter()
+            .any(|provider| last_segment(provider) == last_segment(&resolved))
+        {
+            warnings.push(format!(
+                "Ignoring #[{}] on {}: it resolves to `{}`, which is not one of the provider attributes {:?}. \
+                 Re-exports are not followed; import the attribute from its defining crate \
+                 or add the path to `provider_attributes`",
+                attr.path().to_token_stream().to_string().replace(' ', ""),
+                item,
+                resolved,
+                options.provider_attributes
+            ));
+        }
+    }
+}
+
+/// Parses the `#[provider]` associated functions of an inherent `impl` block.
+///
+/// Their paths are recorded as `crate::<mod>::<Type>::<fn>`, and `Self` in their
+/// signatures is replaced with the impl type. Methods taking `self` cannot be
+/// called without an instance and are skipped.
+fn parse_impl_providers(
+    item_impl: &ItemImpl,
+    scope: &Scope,
+    imports: &Imports,
+    parsed: &mut ParsedModule,
+) {
+    let (cfg, mod_path) = (&scope.options.cfg, scope.mod_path);
+    for item in &item_impl.items {
+        let syn::ImplItem::Fn(func) = item else {
+            continue;
+        };
+        if !cfg.is_enabled(&func.attrs) {
+            continue;
+        }
+        let self_ty = item_impl.self_ty.to_token_stream().to_string();
+        let name = format!("{}::{}", self_ty, func.sig.ident).replace(' ', "");
+        let attrs = effective_attrs(&func.attrs, cfg);
+        if !is_provider(&attrs, scope.options, imports) {
+            warn_unresolved_provider_attrs(
+                &attrs,
+                &name,
+                scope.options,
+                imports,
+                &mut parsed.warnings,
+            );
+            continue;
+        }
+
+        if item_impl.trait_.is_some() {
+            parsed.warnings.push(format!(
+                "Skipping provider {}: providers in trait impls are not supported",
+                name
+            ));
+            continue;
+        }
+        if func.sig.receiver().is_some() {
+            parsed.warnings.push(format!(
+                "Skipping provider {}: providers cannot take `self`",
+                name
+            ));
+            continue;
+        }
+        let Some(type_path) = impl_type_path(&item_impl.self_ty, mod_path, imports) else {
+            parsed.warnings.push(format!(
+                "Skipping provider {}: the impl type must be a plain path without generics",
+                name
+            ));
+            continue;
+        };
+
+        let mut sig = func.sig.clone();
+        ReplaceSelf(&item_impl.self_ty).visit_signature_mut(&mut sig);
+        let path = format!("{}::{}", type_path, func.sig.ident);
+        let vis = effective_vis(scope.vis, &func.vis, mod_path);
+        match parse_provider(
+            &func.attrs,
+            &sig,
+            path,
+            vis,
+            Some(&item_impl.self_ty),
+            scope,
+            imports,
+        ) {
+            Ok(provider) => parsed.providers.extend(provider),
+            Err(e) => parsed.push_error(scope.file, e),
+        }
+    }
+}
+
+/// Combines the visibility of an item in `mod_path` with the effective visibility
+/// of its module (`module_vis`), see [`ProviderInfo::vis`].
+///
+/// For associated functions, the visibility of the impl type is applied once all
+/// files are scanned, as the type may be defined in another one.
+fn effective_vis(module_vis: &str, vis: &syn::Visibility, mod_path: &str) -> String {
+    let module = if mod_path.is_empty() {
+        "crate".to_string()
+    } else {
+        format!("crate::{}", mod_path)
+    };
+    let item_vis = match vis {
+        syn::Visibility::Public(_) => PUBLIC.to_string(),
+        syn::Visibility::Inherited => module,
+        syn::Visibility::Restricted(restricted) => {
+            let mut scope: Vec<String> = module.split("::").map(str::to_string).collect();
+            for segment in restricted.path.segments.iter().map(|s| s.ident.to_string()) {
+                match segment.as_str() {
+                    "crate" => scope.truncate(1),
+                    "self" => {}
+                    "super" if scope.len() > 1 => {
+                        scope.pop();
+                    }
+                    _ => scope.push(segment),
+                }
+            }
+            scope.join("::")
+        }
+    };
+
+    narrowest_vis(module_vis, &item_vis).to_string()
+}
+
+/// The more restrictive of two visibilities that are both ancestors of the same
+/// module (or public), which is the longer one.
+pub(crate) fn narrowest_vis<'a>(a: &'a str, b: &'a str) -> &'a str {
+    if a == PUBLIC || (b != PUBLIC && b.len() > a.len()) {
+        b
+    } else {
+        a
+    }
+}
+
+/// Resolves the type of an `impl` block to a `crate::` path, following the imports
+/// of the module and `crate`, `self` and `super` prefixes. Generic types are not supported.
+fn impl_type_path(self_ty: &Type, mod_path: &str, imports: &Imports) -> Option<String> {
+    let Type::Path(type_path) = self_ty else {
+        return None;
+    };
+    if type_path.qself.is_some()
+        || type_path
+            .path
+            .segments
+            .iter()
+            .any(|s| !s.arguments.is_empty())
+    {
+        return None;
+    }
+
+    let mut segments: Vec<String> = type_path
+        .path
+        .segments
+        .iter()
+        .map(|s| s.ident.to_string())
+        .collect();
+    if type_path.path.leading_colon.is_none()
+        && let Some(full) = imports.names.get(&segments[0])
+    {
+        let imported = full
+            .trim_start_matches("::")
+            .split("::")
+            .map(str::to_string);
+        segments.splice(..1, imported);
+    }
+    Some(crate_path(segments, mod_path)?.join("::"))
+}
+
+/// Resolves a path written in the module `mod_path` to the segments of a `crate::`
+/// path. Leading `crate`, `self` and `super` segments are followed, other paths are
+/// relative to the module.
+fn crate_path(segments: Vec<String>, mod_path: &str) -> Option<Vec<String>> {
+    let mut module: Vec<String> = mod_path
+        .split("::")
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect();
+    let mut segments = segments.into_iter().peekable();
+    match segments.peek().map(String::as_str) {
+        Some("crate") => {
+            module.clear();
+            segments.next();
+        }
+        Some("self") => {
+            segments.next();
+        }
+        _ => {}
+    }
+    while segments.peek().map(String::as_str) == Some("super") {
+        module.pop()?;
+        segments.next();
+    }
+    Some(
+        std::iter::once("crate".to_string())
+            .chain(module)
+            .chain(segments)
+            .collect(),
+    )
+}
+
+/// Rewrites the paths of an expression in the module `mod_path` that refer to items
+/// of the crate, through its imports, `self`, `super` or `Self`, to `crate::` paths
+/// that resolve the same in any module. Other paths, e.g. of local variables, the
+/// prelude or other crates, are left alone.
+struct AbsolutePaths<'a> {
+    mod_path: &'a str,
+    imports: &'a Imports,
+    self_ty: Option<&'a Type>,
+}
+
+impl AbsolutePaths<'_> {
+    /// Only the trait of `<T as Trait>::f` is a path to resolve, the `f` of `<T>::f`
+    /// is an associated item of `T`.
+    fn visit_qualified_path(&mut self, qself: &mut Option<syn::QSelf>, path: &mut syn::Path) {
+        let Some(qself) = qself else {
+            return self.visit_path_mut(path);
+        };
+        self.visit_type_mut(&mut qself.ty);
+        if qself.position > 0 {
+            let len = path.segments.len();
+            self.visit_path_mut(path);
+            qself.position += path.segments.len() - len;
+        } else {
+            for segment in &mut path.segments {
+                self.visit_path_arguments_mut(&mut segment.arguments);
+            }
+        }
+    }
+
+    /// The segments to replace the first segment of `path` with, if any.
+    fn resolve_first(&self, path: &syn::Path) -> Option<Vec<String>> {
+        if path.leading_colon.is_some() {
+            return None;
+        }
+        let first = path.segments[0].ident.to_string();
+        let (segments, local) = match first.as_str() {
+            "crate" => return None,
+            "self" | "super" => (vec![first], true),
+            "Self" => {
+                let self_path = impl_type_path(self.self_ty?, self.mod_path, self.imports)?;
+                return Some(self_path.split("::").map(str::to_string).collect());
+            }
+            _ => match self.imports.names.get(&first) {
+                Some(full) if full.starts_with("::") => return None,
+                Some(full) => {
+                    let segments: Vec<String> = full.split("::").map(str::to_string).collect();
+                    let local = matches!(segments[0].as_str(), "crate" | "self" | "super")
+                        || self.imports.items.contains(&segments[0]);
+                    (segments, local)
+                }
+                None => (vec![first.clone()], self.imports.items.contains(&first)),
+            },
+        };
+        if local {
+            crate_path(segments, self.mod_path)
+        } else {
+            Some(segments)
+        }
+    }
+}
+
+impl VisitMut for AbsolutePaths<'_> {
+    fn visit_path_mut(&mut self, path: &mut syn::Path) {
+        if let Some(replacement) = self.resolve_first(path) {
+            let span = path.segments[0].ident.span();
+            let first = path
+                .segments
+                .iter()
+                .next()
+                .cloned()
+                .expect("paths are not empty");
+            let rest: Vec<syn::PathSegment> = path.segments.iter().skip(1).cloned().collect();
+            let mut segments: syn::punctuated::Punctuated<syn::PathSegment, syn::Token![::]> =
+                replacement
+                    .iter()
+                    .map(|s| syn::PathSegment::from(syn::Ident::new(s, span)))
+                    .collect();
+            if let Some(last) = segments.last_mut() {
+                last.arguments = first.arguments;
+            }
+            segments.extend(rest);
+            path.segments = segments;
+        }
+        syn::visit_mut::visit_path_mut(self, path);
+    }
+
+    fn visit_expr_path_mut(&mut self, expr: &mut syn::ExprPath) {
+        expr.attrs
+            .iter_mut()
+            .for_each(|attr| self.visit_attribute_mut(attr));
+        self.visit_qualified_path(&mut expr.qself, &mut expr.path);
+    }
+
+    fn visit_type_path_mut(&mut self, ty: &mut syn::TypePath) {
+        self.visit_qualified_path(&mut ty.qself, &mut ty.path);
+    }
+}
+
+/// Replaces `Self` in a signature with the type of the enclosing `impl` block.
+struct ReplaceSelf<'a>(&'a Type);
+
+impl VisitMut for ReplaceSelf<'_> {
+    fn visit_type_mut(&mut self, ty: &mut Type) {
+        if let Type::Path(type_path) = ty
+            && type_path.qself.is_none()
+            && type_path.path.is_ident("Self")
+        {
+            *ty = self.0.clone();
+            return;
+        }
+        syn::visit_mut::visit_type_mut(self, ty);
+    }
+}
+
+/// Parses a function with the `#[provider]` attribute in `scope`, given its full `path`
+/// and effective visibility. `self_ty` is the impl type for associated functions.
+/// Malformed `#[provider(...)]`, `#[bind]`, `#[inject]` and `#[wire(from = ...)]` attributes are errors
+/// rather than being ignored, which would silently fall back to the default binding.
+fn parse_provider(
+    attrs: &[syn::Attribute],
+    sig: &Signature,
+    path: String,
+    vis: String,
+    self_ty: Option<&Type>,
+    scope: &Scope,
+    imports: &Imports,
+) -> syn::Result<Option<ProviderInfo>> {
+    let options = scope.options;
+    let cfg = &options.cfg;
+    let attrs = effective_attrs(attrs, cfg);
+    if !is_provider(&attrs, options, imports) {
+        return Ok(None);
+    }
+
+    let mut errors: Option<syn::Error> = None;
+    let mut check = |result: syn::Result<Option<Type>>| match result {
+        Ok(ty) => ty,
+        Err(e) => {
+            match &mut errors {
+                Some(errors) => errors.combine(e),
+                None => errors = Some(e),
+            }
+            None
+        }
+    };
+
+    let mut args = Vec::new();
+    for (index, arg) in sig.inputs.iter().enumerate() {
+        let FnArg::Typed(pat_type) = arg else {
+            continue;
+        };
+        let name = argument_name(index, &pat_type.pat);
+        let ty = pat_type.ty.to_token_stream().to_string();
+        let mut from = None;
+        for attr in effective_attrs(&pat_type.attrs, cfg) {
+            if let Some(from_ty) = check(parse_inject_attr(&attr)) {
+                from = Some(from_ty.to_token_stream().to_string());
+            }
+        }
+        args.push(ProviderArgument { name, ty, from });
+    }
+
+    let (ret, is_result, is_option) = match &sig.output {
+        syn::ReturnType::Type(_, ty) => split_output(ty),
+        syn::ReturnType::Default => ("()".to_string(), false, false),
+    };
+
+    // Only `wire::provider` takes arguments; custom provider attributes are plain markers.
+    let provider_attr = attrs
+        .iter()
+        .find(|attr| {
+            imports.resolve(attr.path()) == PROVIDER_ATTRIBUTE
+                && matches!(attr.meta, syn::Meta::List(_))
+        })
+        .map(|attr| attr.parse_args::<ProviderAttr>());
+    let map_err = match provider_attr {
+        Some(Ok(provider_attr)) => provider_attr.map_err.map(|mut expr| {
+            // The expression is pasted into injectors in other modules.
+            AbsolutePaths {
+                mod_path: scope.mod_path,
+                imports,
+                self_ty,
+            }
+            .visit_expr_mut(&mut expr);
+            expr.to_token_stream().to_string()
+        }),
+        Some(Err(e)) => {
+            check(Err(e));
+            None
+        }
+        None => None,
+    };
+
+    // `#[bind]` is a helper of `#[provider]` (which strips it), so it is never a path.
+    let mut bindings = Vec::new();
+    for attr in &attrs {
+        if let Some(mut ty) = check(parse_bind_attr(attr)) {
+            if let Some(self_ty) = self_ty {
+                ReplaceSelf(self_ty).visit_type_mut(&mut ty);
+            }
+            bindings.push(ty.to_token_stream().to_string());
+        }
+    }
+
+    match errors {
+        Some(errors) => Err(errors),
+        None => Ok(Some(ProviderInfo {
+            path,
+            vis,
+            args,
+            ret,
+            is_result,
+            is_option,
+            bindings,
+            map_err,
+        })),
+    }
+}
+
+/// Parses an injector function with the `#[wire]` attribute.
+fn parse_injector(
+    func: &ItemFn,
+    mod_path: &str,
+    cfg: &CfgEnv,
+    imports: &Imports,
+    warnings: &mut Vec<String>,
+) -> Option<InjectorInfo> {
+    let attrs = effective_attrs(&func.attrs, cfg);
+    let attr = attrs
+        .iter()
+        .find(|attr| imports.resolve(attr.path()) == INJECTOR_ATTRIBUTE)?;
+
+    let wire_attr = match &attr.meta {
+        syn::Meta::List(list) => match list.parse_args::<WireAttr>() {
+            Ok(wire_attr) => wire_attr,
+            Err(e) => {
+                warnings.push(format!(
+                    "Skipping injector {} due to invalid #[wire] arguments: {}",
+                    func.sig.ident, e
+                ));
+                return None;
+            }
+        },
+        _ => WireAttr::default(),
+    };
+
+    let syn::ReturnType::Type(_, ty) = &func.sig.output else {
+        return None;
+    };
+    let (ret, is_result, is_option) = split_output(ty);
+
+    Some(InjectorInfo {
+        path: item_path(mod_path, &func.sig.ident.to_string()),
+        vis: func.vis.to_token_stream().to_string(),
+        sig: func.sig.to_token_stream().to_string(),
+        ret,
+        is_result,
+        is_option,
+        wrappers: wire_attr.wrappers,
+        file: wire_attr.file,
+        error: wire_attr.error.map(|error| error.to_string()),
+        line: func.sig.ident.span().start().line,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Scans `source` as the crate root `src/lib.rs`, with only the `wire` cfg set.
+    fn scan(source: &str) -> ParsedModule {
+        scan_with(source, &ScanOptions::new(CfgEnv::default()))
+    }
+
+    fn scan_with(source: &str, options: &ScanOptions) -> ParsedModule {
+        let ast = syn::parse_file(source).unwrap();
+        let module = ModuleFile::root(PathBuf::from("src/lib.rs"));
+        parse_module(&ast, &module, options)
+    }
+
+    fn provider_paths(parsed: &ParsedModule) -> Vec<&str> {
+        parsed.providers.iter().map(|p| p.path.as_str()).collect()
+    }
+
+    #[test]
+    fn provider_attribute_by_path_alias_and_glob() {
+        let parsed = scan(
+            r#"
+            #[wire::provider]
+            pub fn full() -> A { A }
+            #[::wire::provider]
+            pub fn absolute() -> B { B }
+            mod aliased {
+                use wire::provider as make;
+                #[make]
+                pub fn alias() -> C { C }
+            }
+            mod renamed_crate {
+                extern crate wire as di;
+                #[di::provider]
+                pub fn extern_alias() -> D { D }
+            }
+            mod globbed {
+                use wire::*;
+                #[provider]
+                pub fn glob() -> E { E }
+            }
+            "#,
+        );
+        assert_eq!(
+            provider_paths(&parsed),
+            [
+                "crate::full",
+                "crate::absolute",
+                "crate::aliased::alias",
+                "crate::renamed_crate::extern_alias",
+                "crate::globbed::glob",
+            ]
+        );
+        assert_eq!(parsed.warnings, Vec::<String>::new());
+    }
+
+    #[test]
+    fn imports_are_scoped_to_their_inline_module() {
+        let parsed = scan(
+            r#"
+            use other::provider;
+            #[provider]
+            pub fn outer() -> A { A }
+            mod inner {
+                #[provider]
+                pub fn inner() -> B { B }
+                mod nested {
+                    use wire::provider as make;
+                    #[make]
+                    pub fn nested() -> C { C }
+                }
+            }
+            "#,
+        );
+        assert_eq!(
+            provider_paths(&parsed),
+            ["crate::inner::inner", "crate::inner::nested::nested"]
+        );
+        assert_eq!(parsed.warnings.len(), 1);
+        assert!(parsed.warnings[0].contains("#[provider] on crate::outer"));
+    }
+
+    #[test]
+    fn re_exported_provider_attribute_is_reported() {
+        let parsed = scan(
+            r#"
+            use crate::prelude::provider;
+            use crate::prelude::provider as make;
+            #[provider]
+            pub fn db() -> Db { Db }
+            pub struct Cache;
+            impl Cache {
+                #[make]
+                pub fn new() -> Self { Cache }
+                #[inline]
+                pub fn plain() -> Self { Cache }
+            }
+            pub fn unrelated() {}
+            "#,
+        );
+        assert!(parsed.providers.is_empty());
+        assert_eq!(parsed.warnings.len(), 2, "{:?}", parsed.warnings);
+        assert!(
+            parsed.warnings[0].starts_with(
+                "Ignoring #[provider] on crate::db: it resolves to `crate::prelude::provider`"
+            ),
+            "{}",
+            parsed.warnings[0]
+        );
+        assert!(parsed.warnings[1].starts_with("Ignoring #[make] on Cache::new"));
+    }
+
+    #[test]
+    fn custom_provider_attributes() {
+        let mut options = ScanOptions::new(CfgEnv::default());
+        options.provider_attributes =
+            vec!["wire::provider".to_string(), "di::component".to_string()];
+        let parsed = scan_with(
+            r#"
+            use di::component;
+            #[component]
+            pub fn db() -> Db { Db }
+            mod inner {
+                use di::prelude::component;
+                #[component]
+                pub fn cache() -> Cache { Cache }
+            }
+            "#,
+            &options,
+        );
+        assert_eq!(provider_paths(&parsed), ["crate::db"]);
+        assert_eq!(parsed.warnings.len(), 1);
+        assert!(parsed.warnings[0].contains("`di::prelude::component`"));
+    }
+
+    #[test]
+    fn impl_on_an_imported_type() {
+        let parsed = scan(
+            r#"
+            mod models {
+                pub struct Db;
+                pub struct Cache;
+            }
+            pub mod services {
+                use crate::models::Db;
+                use super::models::Cache as Store;
+                use self::local::Local;
+                impl Db {
+                    #[provider]
+                    pub fn connect() -> Self { Db }
+                }
+                impl Store {
+                    #[provider]
+                    pub fn open() -> Store { Store }
+                }
+                impl Local {
+                    #[provider]
+                    pub fn new() -> Self { Local }
+                }
+                pub mod local {
+                    pub struct Local;
+                }
+            }
+            "#,
+        );
+        assert_eq!(
+            provider_paths(&parsed),
+            [
+                "crate::models::Db::connect",
+                "crate::models::Cache::open",
+                "crate::services::local::Local::new",
+            ]
+        );
+    }
+
+    fn vis(source: &str) -> syn::Visibility {
+        syn::parse_str(source).unwrap()
+    }
+
+    #[test]
+    fn effective_vis_of_items() {
+        // Public modules pass the item's visibility through.
+        assert_eq!(effective_vis(PUBLIC, &vis("pub"), "a::b"), PUBLIC);
+        assert_eq!(effective_vis(PUBLIC, &vis(""), "a::b"), "crate::a::b");
+        assert_eq!(effective_vis(PUBLIC, &vis(""), ""), "crate");
+        assert_eq!(effective_vis(PUBLIC, &vis("pub(crate)"), "a::b"), "crate");
+        assert_eq!(
+            effective_vis(PUBLIC, &vis("pub(self)"), "a::b"),
+            "crate::a::b"
+        );
+        assert_eq!(
+            effective_vis(PUBLIC, &vis("pub(super)"), "a::b"),
+            "crate::a"
+        );
+        assert_eq!(
+            effective_vis(PUBLIC, &vis("pub(in crate::a)"), "a::b"),
+            "crate::a"
+        );
+        assert_eq!(
+            effective_vis(PUBLIC, &vis("pub(in super::super)"), "a::b"),
+            "crate"
+        );
+    }
+
+    #[test]
+    fn effective_vis_is_limited_by_the_module() {
+        assert_eq!(effective_vis("crate::a", &vis("pub"), "a::b"), "crate::a");
+        assert_eq!(
+            effective_vis("crate::a", &vis("pub(crate)"), "a::b"),
+            "crate::a"
+        );
+        assert_eq!(effective_vis("crate::a", &vis(""), "a::b"), "crate::a::b");
+        assert_eq!(
+            effective_vis("crate", &vis("pub(super)"), "a::b"),
+            "crate::a"
+        );
+    }
+
+    #[test]
+    fn visibility_of_modules_types_and_associated_functions() {
+        let parsed = scan(
+            r#"
+            mod private {
+                pub mod nested {
+                    pub struct Db;
+                    pub(crate) enum Mode {}
+                    type Alias = Db;
+                    impl Db {
+                        #[provider]
+                        pub fn connect() -> Self { Db }
+                        #[provider]
+                        fn local() -> Self { Db }
+                    }
+                }
+            }
+            pub struct Api;
+            "#,
+        );
+        let types: Vec<(&str, &str)> = parsed
+            .types
+            .iter()
+            .map(|(path, vis)| (path.as_str(), vis.as_str()))
+            .collect();
+        assert_eq!(
+            types,
+            [
+                ("crate::Api", PUBLIC),
+                ("crate::private::nested::Alias", "crate::private::nested"),
+                ("crate::private::nested::Db", "crate"),
+                ("crate::private::nested::Mode", "crate"),
+            ]
+        );
+        let vis: Vec<&str> = parsed.providers.iter().map(|p| p.vis.as_str()).collect();
+        assert_eq!(vis, ["crate", "crate::private::nested"]);
+    }
+
+    #[test]
+    fn narrowest_vis_is_the_innermost_module() {
+        assert_eq!(narrowest_vis(PUBLIC, "crate::a"), "crate::a");
+        assert_eq!(narrowest_vis("crate::a", PUBLIC), "crate::a");
+        assert_eq!(narrowest_vis("crate", "crate::a"), "crate::a");
+        assert_eq!(narrowest_vis("crate::a::b", "crate::a"), "crate::a::b");
+        assert_eq!(narrowest_vis(PUBLIC, PUBLIC), PUBLIC);
+    }
+
+    fn map_errs(parsed: &ParsedModule) -> Vec<String> {
+        parsed
+            .providers
+            .iter()
+            .map(|p| p.map_err.clone().unwrap_or_default().replace(' ', ""))
+            .collect()
+    }
+
+    #[test]
+    fn map_err_is_resolved_in_the_module_of_the_provider() {
+        let parsed = scan(
+            r#"
+            pub mod db {
+                use crate::errors::AppError;
+                use super::errors::wrap as wrap_error;
+                use errors_in_db::Local;
+                use anyhow::Error;
+                mod errors_in_db {
+                    pub struct Local;
+                }
+                fn convert(e: sqlx::Error) -> AppError { todo!() }
+
+                #[provider(map_err = AppError::Db)]
+                pub fn imported() -> Result<A, E> { todo!() }
+                #[provider(map_err = wrap_error)]
+                pub fn renamed() -> Result<B, E> { todo!() }
+                #[provider(map_err = convert)]
+                pub fn local_fn() -> Result<C, E> { todo!() }
+                #[provider(map_err = self::convert)]
+                pub fn self_path() -> Result<D, E> { todo!() }
+                #[provider(map_err = super::errors::wrap)]
+                pub fn super_path() -> Result<F, E> { todo!() }
+                #[provider(map_err = |e| AppError::Other(Box::new(e), Local))]
+                pub fn closure() -> Result<G, E> { todo!() }
+                #[provider(map_err = Error::from)]
+                pub fn other_crate() -> Result<H, E> { todo!() }
+                #[provider(map_err = ::anyhow::Error::new)]
+                pub fn absolute() -> Result<I, E> { todo!() }
+                #[provider(map_err = <AppError as From<E>>::from)]
+                pub fn qualified() -> Result<J, E> { todo!() }
+                #[provider(map_err = <AppError as self::Convert>::convert)]
+                pub fn qualified_local_trait() -> Result<K, E> { todo!() }
+                #[provider(map_err = <AppError>::from)]
+                pub fn qualified_self() -> Result<L, E> { todo!() }
+            }
+            "#,
+        );
+        assert_eq!(
+            map_errs(&parsed),
+            [
+                "crate::errors::AppError::Db",
+                "crate::errors::wrap",
+                "crate::db::convert",
+                "crate::db::convert",
+                "crate::errors::wrap",
+                "|e|crate::errors::AppError::Other(Box::new(e),crate::db::errors_in_db::Local)",
+                "anyhow::Error::from",
+                "::anyhow::Error::new",
+                "<crate::errors::AppErrorasFrom<E>>::from",
+                "<crate::errors::AppErrorascrate::db::Convert>::convert",
+                "<crate::errors::AppError>::from",
+            ]
+        );
+    }
+
+    #[test]
+    fn map_err_of_an_associated_function() {
+        let parsed = scan(
+            r#"
+            pub mod db {
+                use crate::models::Pool;
+                pub struct Db;
+                impl Db {
+                    #[provider(map_err = Self::wrap)]
+                    pub fn connect() -> Result<Self, E> { todo!() }
+                    fn wrap(e: E) -> AppError { todo!() }
+                }
+                impl Pool {
+                    #[provider(map_err = Self::wrap::<E>)]
+                    pub fn open() -> Result<Self, E> { todo!() }
+                }
+            }
+            "#,
+        );
+        assert_eq!(
+            map_errs(&parsed),
+            ["crate::db::Db::wrap", "crate::models::Pool::wrap::<E>"]
+        );
+    }
+
+    #[test]
+    fn cfg_attr_is_unwrapped_when_its_predicate_holds() {
+        let parsed = scan(
+            r#"
+            #[cfg_attr(wire, provider)]
+            pub fn db() -> Db { Db }
+            #[cfg_attr(all(wire, not(feature = "mock")), wire::provider, bind(dyn Repo))]
+            pub fn sql() -> Sql { Sql }
+            #[cfg_attr(feature = "mock", provider)]
+            pub fn mock() -> Mock { Mock }
+            "#,
+        );
+        assert_eq!(provider_paths(&parsed), ["crate::db", "crate::sql"]);
+        assert_eq!(parsed.providers[1].bindings, ["dyn Repo"]);
+    }
+
+    #[test]
+    fn cfg_attr_path_of_a_module() {
+        let parsed = scan(
+            r#"
+            #[cfg_attr(wire, path = "generated/wired.rs")]
+            mod wired;
+            mod inline {
+                #[cfg_attr(not(wire), path = "other.rs")]
+                mod nested;
+            }
+            "#,
+        );
+        assert_eq!(parsed.children.len(), 1);
+        assert_eq!(parsed.children[0].path, Path::new("src/generated/wired.rs"));
+        assert_eq!(
+            parsed.lookups[0].candidates[0],
+            Path::new("src/inline/nested.rs")
+        );
+    }
+}
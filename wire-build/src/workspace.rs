@@ -0,0 +1,225 @@
+//! Workspace-wide scanning driven by `cargo metadata`.
+//!
+//! Every target of every workspace member (libraries, binaries, examples and
+//! tests) is its own crate, so each one gets its own manifest with paths that
+//! are relative to that crate's root.
+
+use crate::{InjectorInfo, PRODUCER, ProviderInfo, ProviderManifest, scan_target};
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use wire_core::models::PUBLIC;
+
+/// Target kinds reported by `cargo metadata` that are scanned.
+const LIB_KINDS: &[&str] = &["lib", "rlib", "dylib", "cdylib", "staticlib"];
+const SCANNED_KINDS: &[&str] = &["bin", "example", "test"];
+
+#[derive(Deserialize)]
+struct Metadata {
+    packages: Vec<Package>,
+    workspace_members: Vec<String>,
+    target_directory: PathBuf,
+}
+
+#[derive(Deserialize)]
+struct Package {
+    id: String,
+    name: String,
+    targets: Vec<Target>,
+    dependencies: Vec<Dependency>,
+}
+
+#[derive(Deserialize)]
+struct Dependency {
+    name: String,
+    rename: Option<String>,
+    /// `None` for normal dependencies, `"dev"` or `"build"` otherwise.
+    kind: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Target {
+    name: String,
+    kind: Vec<String>,
+    src_path: PathBuf,
+}
+
+/// The providers and injectors of a single crate in the workspace.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CrateManifest {
+    pub package: String,
+    pub target: String,
+    pub kind: String,
+    pub root: PathBuf,
+    pub providers: Vec<ProviderInfo>,
+    pub injectors: Vec<InjectorInfo>,
+    /// Warnings of the scan, see [`Scan::warnings`](crate::Scan::warnings).
+    pub warnings: Vec<String>,
+}
+
+impl CrateManifest {
+    /// File name used by [`generate_workspace`], e.g. `bin-server.json`.
+    pub fn file_name(&self) -> String {
+        format!("{}-{}.json", self.kind, self.target)
+    }
+}
+
+/// Scans every target of every member of the workspace containing `manifest_dir`.
+///
+/// The root of each target comes from `cargo metadata`, so `[lib] path` and
+/// `[[bin]] path` overrides are respected. Each crate also sees the public providers
+/// of the libraries it can use: its own package's library for binaries, examples and
+/// tests, and the libraries of workspace members it depends on, with paths
+/// rewritten to `::<lib_name>::...`. Scan caches are kept per target in `cache_dir`.
+pub fn scan_workspace(manifest_dir: &Path, cache_dir: &Path) -> Result<Vec<CrateManifest>> {
+    let metadata = cargo_metadata(manifest_dir)?;
+    let members: Vec<&Package> = metadata
+        .packages
+        .iter()
+        .filter(|p| metadata.workspace_members.contains(&p.id))
+        .collect();
+
+    // Library providers of each member, keyed by package name, with `crate::` paths.
+    let mut lib_exports: HashMap<&str, (String, Vec<ProviderInfo>)> = HashMap::new();
+    let mut manifests = Vec::new();
+
+    for package in &members {
+        let package_cache = cache_dir.join(&package.name);
+        fs::create_dir_all(&package_cache)?;
+
+        for target in &package.targets {
+            let Some(kind) = target
+                .kind
+                .iter()
+                .find(|k| LIB_KINDS.contains(&k.as_str()) || SCANNED_KINDS.contains(&k.as_str()))
+            else {
+                continue;
+            };
+            let kind = if LIB_KINDS.contains(&kind.as_str()) {
+                "lib"
+            } else {
+                kind.as_str()
+            };
+
+            let cache_path = package_cache.join(format!("{}-{}_cache.json", kind, target.name));
+            let scan = scan_target(&target.src_path, &cache_path).with_context(|| {
+                format!(
+                    "failed to scan {} target `{}` of `{}`",
+                    kind, target.name, package.name
+                )
+            })?;
+
+            let mut providers: Vec<ProviderInfo> = scan
+                .cache
+                .values()
+                .flat_map(|r| r.providers.clone())
+                .collect();
+            let mut injectors: Vec<InjectorInfo> = scan
+                .cache
+                .values()
+                .flat_map(|r| r.injectors.clone())
+                .collect();
+            providers.sort_by(|a, b| a.path.cmp(&b.path));
+            injectors.sort_by(|a, b| a.path.cmp(&b.path));
+
+            if kind == "lib" {
+                lib_exports.insert(
+                    &package.name,
+                    (target.name.replace('-', "_"), providers.clone()),
+                );
+            }
+
+            manifests.push(CrateManifest {
+                package: package.name.clone(),
+                target: target.name.clone(),
+                kind: kind.to_string(),
+                root: target.src_path.clone(),
+                providers,
+                injectors,
+                warnings: scan.warnings,
+            });
+        }
+    }
+
+    for manifest in &mut manifests {
+        let package = members
+            .iter()
+            .find(|p| p.name == manifest.package)
+            .expect("manifest of a member");
+        let is_dev = manifest.kind == "test" || manifest.kind == "example";
+
+        let mut libs = Vec::new();
+        if manifest.kind != "lib"
+            && let Some((lib_name, providers)) = lib_exports.get(package.name.as_str())
+        {
+            libs.push((lib_name.clone(), providers));
+        }
+        for dep in &package.dependencies {
+            let usable = match dep.kind.as_deref() {
+                None => true,
+                Some("dev") => is_dev,
+                _ => false,
+            };
+            if usable && let Some((lib_name, providers)) = lib_exports.get(dep.name.as_str()) {
+                let name = dep
+                    .rename
+                    .as_ref()
+                    .map_or(lib_name.clone(), |r| r.replace('-', "_"));
+                libs.push((name, providers));
+            }
+        }
+
+        for (lib_name, providers) in libs {
+            manifest.providers.extend(
+                providers
+                    .iter()
+                    .filter(|p| p.vis == PUBLIC)
+                    .filter_map(|p| p.exported_as(&lib_name)),
+            );
+        }
+    }
+
+    Ok(manifests)
+}
+
+/// Scans the workspace and writes one manifest per crate to
+/// `out_dir/<package>/<kind>-<target>.json`.
+pub fn generate_workspace(manifest_dir: &Path, out_dir: &Path) -> Result<Vec<CrateManifest>> {
+    let manifests = scan_workspace(manifest_dir, &out_dir.join("cache"))?;
+    for manifest in &manifests {
+        let dir = out_dir.join(&manifest.package);
+        fs::create_dir_all(&dir)?;
+        let providers = ProviderManifest {
+            injectors: manifest.injectors.clone(),
+            ..ProviderManifest::new(PRODUCER, manifest.providers.clone())
+        };
+        fs::write(dir.join(manifest.file_name()), providers.to_json())?;
+    }
+    Ok(manifests)
+}
+
+/// The target directory of the workspace containing `manifest_dir`, as reported by
+/// `cargo metadata`, which follows `CARGO_TARGET_DIR` and `build.target-dir`.
+pub fn target_dir(manifest_dir: &Path) -> Result<PathBuf> {
+    Ok(cargo_metadata(manifest_dir)?.target_directory)
+}
+
+fn cargo_metadata(manifest_dir: &Path) -> Result<Metadata> {
+    let cargo = env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+    let output = Command::new(cargo)
+        .args(["metadata", "--format-version", "1", "--no-deps"])
+        .current_dir(manifest_dir)
+        .output()
+        .context("failed to run `cargo metadata`")?;
+    if !output.status.success() {
+        bail!(
+            "`cargo metadata` failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    Ok(serde_json::from_slice(&output.stdout)?)
+}
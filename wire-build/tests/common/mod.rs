@@ -0,0 +1,43 @@
+// Compiled into each test crate, which only uses some of the helpers.
+#![allow(dead_code)]
+
+use std::fs;
+use std::path::PathBuf;
+
+/// A crate written to a temporary directory, removed again when dropped.
+pub struct Fixture {
+    pub dir: PathBuf,
+}
+
+impl Fixture {
+    /// Writes `files`, given as paths relative to the fixture and their contents.
+    pub fn new(name: &str, files: &[(&str, &str)]) -> Self {
+        let dir = std::env::temp_dir().join(format!("wire-build-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let fixture = Fixture { dir };
+        for (path, content) in files {
+            fixture.write(path, content);
+        }
+        fixture
+    }
+
+    pub fn path(&self, path: &str) -> PathBuf {
+        self.dir.join(path)
+    }
+
+    pub fn write(&self, path: &str, content: &str) {
+        let path = self.path(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    pub fn cache_path(&self) -> PathBuf {
+        self.dir.join("wire_cache.json")
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
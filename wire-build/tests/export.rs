@@ -0,0 +1,92 @@
+//! Providers of a fixture crate as exported to the crates that depend on it.
+
+mod common;
+
+use common::Fixture;
+use wire_build::ProviderInfo;
+
+fn scan(fixture: &Fixture) -> Vec<ProviderInfo> {
+    let scan = wire_build::scan_target(&fixture.path("src/lib.rs"), &fixture.cache_path()).unwrap();
+    let mut providers: Vec<ProviderInfo> = scan
+        .cache
+        .values()
+        .flat_map(|r| r.providers.clone())
+        .collect();
+    providers.sort_by(|a, b| a.path.cmp(&b.path));
+    providers
+}
+
+#[test]
+fn crate_paths_are_rebased_onto_the_crate_name() {
+    let fixture = Fixture::new(
+        "export",
+        &[
+            ("src/lib.rs", "pub mod db;\npub mod errors;\n"),
+            ("src/errors.rs", "pub enum Error { Db(std::io::Error) }\n"),
+            (
+                "src/db.rs",
+                r#"
+                use crate::errors::Error;
+                use std::sync::Arc;
+
+                pub trait Repo {}
+                pub struct Pool;
+                pub struct Sql;
+                impl Repo for Sql {}
+
+                #[wire::provider(map_err = Error::Db)]
+                #[bind(Arc<dyn crate::db::Repo>)]
+                pub fn sql(
+                    #[inject(Arc<crate::db::Pool>)] pool: &Pool,
+                    config: Option<&crate::Config>,
+                    (name, _): (String, u8),
+                ) -> Result<Arc<crate::db::Sql>, std::io::Error> {
+                    Ok(Arc::new(Sql))
+                }
+                "#,
+            ),
+        ],
+    );
+    let providers = scan(&fixture);
+    let sql = providers[0].exported_as("my_infra").unwrap();
+
+    assert_eq!(sql.path, "::my_infra::db::sql");
+    assert_eq!(sql.ret, "Arc < :: my_infra :: db :: Sql >");
+    assert_eq!(sql.bindings, ["Arc < dyn :: my_infra :: db :: Repo >"]);
+    assert_eq!(
+        sql.map_err.as_deref(),
+        Some(":: my_infra :: errors :: Error :: Db")
+    );
+    let args: Vec<(&str, &str, Option<&str>)> = sql
+        .args
+        .iter()
+        .map(|arg| (arg.name.as_str(), arg.ty.as_str(), arg.from.as_deref()))
+        .collect();
+    assert_eq!(
+        args,
+        [
+            ("pool", "& Pool", Some("Arc < :: my_infra :: db :: Pool >")),
+            ("config", "Option < & :: my_infra :: Config >", None),
+            ("arg2", "(String , u8)", None),
+        ]
+    );
+    // Unqualified types and everything else are kept as they are.
+    assert_eq!(sql.vis, "pub");
+    assert!(sql.is_result);
+    assert!(!sql.is_option);
+}
+
+#[test]
+fn providers_of_other_crates_are_not_exported_again() {
+    let fixture = Fixture::new(
+        "export-again",
+        &[(
+            "src/lib.rs",
+            "pub struct Db;\n#[wire::provider]\npub fn db() -> Db { Db }\n",
+        )],
+    );
+    let exported = scan(&fixture)[0].exported_as("infra").unwrap();
+    assert_eq!(exported.path, "::infra::db");
+    assert_eq!(exported.ret, "Db");
+    assert!(exported.exported_as("app").is_none());
+}
@@ -0,0 +1,98 @@
+//! Injectors generated into a file by `wire-build` instead of the `#[wire]` macro.
+
+mod common;
+
+use common::Fixture;
+use std::fs;
+
+const PROVIDERS: &str = r#"
+    pub struct Db;
+    pub struct App(pub Db);
+
+    #[cfg_attr(wire, provider)]
+    pub fn connect() -> Result<Db, std::io::Error> { Ok(Db) }
+
+    #[cfg_attr(wire, provider)]
+    pub fn app(db: Db) -> App { App(db) }
+"#;
+
+fn generate(fixture: &Fixture) -> anyhow::Result<String> {
+    let output = fixture.path("out/wire_gen.rs");
+    fs::create_dir_all(output.parent().unwrap()).unwrap();
+    wire_build::generate_injectors(fixture.path("src"), &output)?;
+    Ok(fs::read_to_string(output).unwrap())
+}
+
+#[test]
+fn injectors_are_written_as_plain_functions() {
+    let lib = r#"
+        pub mod providers;
+
+        #[cfg(wire)]
+        #[wire]
+        pub fn initialize_app() -> Result<providers::App, std::io::Error> {}
+
+        include!(concat!(env!("OUT_DIR"), "/wire_gen.rs"));
+    "#;
+    let fixture = Fixture::new(
+        "generate",
+        &[("src/lib.rs", lib), ("src/providers.rs", PROVIDERS)],
+    );
+    let generated = generate(&fixture).unwrap();
+    let expected = r#"// @generated by wire-build. Do not edit.
+
+pub fn initialize_app() -> Result<providers::App, std::io::Error> {
+    #[allow(non_snake_case)]
+    fn __wire_convert_error_of_crate_providers_connect<T, E>(
+        result: ::core::result::Result<T, E>,
+    ) -> ::core::result::Result<T, std::io::Error>
+    where
+        E: ::core::convert::Into<std::io::Error>,
+    {
+        result.map_err(::core::convert::Into::into)
+    }
+    let db_0 = __wire_convert_error_of_crate_providers_connect(
+        crate::providers::connect(),
+    )?;
+    let app_1 = crate::providers::app(db_0);
+    Ok(app_1)
+}
+"#;
+    assert_eq!(generated, expected);
+}
+
+#[test]
+fn injectors_of_several_modules_are_rejected() {
+    let stub = |name: &str| {
+        format!(
+            "#[cfg(wire)]\n#[wire]\npub fn {}() -> crate::providers::App {{}}\n",
+            name
+        )
+    };
+    let fixture = Fixture::new(
+        "generate-modules",
+        &[
+            (
+                "src/lib.rs",
+                &format!(
+                    "pub mod providers;\npub mod cli;\n{}{}",
+                    stub("init"),
+                    stub("init_again")
+                ),
+            ),
+            ("src/cli.rs", &stub("run")),
+            (
+                "src/providers.rs",
+                &PROVIDERS
+                    .replace("Result<Db, std::io::Error>", "Db")
+                    .replace("Ok(Db)", "Db"),
+            ),
+        ],
+    );
+    let error = generate(&fixture).unwrap_err().to_string();
+    assert_eq!(
+        error,
+        "generate_injectors writes a single file that is included in one module, but injectors are defined in \
+         crate (crate::init, crate::init_again), crate::cli (crate::cli::run); move them into one module"
+    );
+}
@@ -0,0 +1,103 @@
+//! Walking the module tree of a fixture crate from its root file.
+
+mod common;
+
+use common::Fixture;
+
+fn provider(name: &str) -> String {
+    format!(
+        "pub struct T;\n#[wire::provider]\npub fn {}() -> u8 {{ 0 }}\n",
+        name
+    )
+}
+
+#[test]
+fn module_files_are_found_like_rustc_does() {
+    let fixture = Fixture::new(
+        "modules",
+        &[
+            (
+                "src/lib.rs",
+                r#"
+                pub mod file;
+                pub mod dir;
+                #[path = "custom/renamed.rs"]
+                pub mod attributed;
+                pub mod inline {
+                    #[wire::provider]
+                    pub fn inline() -> u16 { 0 }
+                    pub mod nested;
+                    #[path = "other.rs"]
+                    pub mod attributed_in_inline;
+                }
+                pub mod missing;
+                "#,
+            ),
+            // `foo.rs` with its submodules in `foo/`.
+            (
+                "src/file.rs",
+                &format!("pub mod sub;\n{}", provider("file")),
+            ),
+            ("src/file/sub.rs", &provider("file_sub")),
+            // `foo/mod.rs` with its submodules next to it.
+            (
+                "src/dir/mod.rs",
+                &format!("pub mod sub;\n{}", provider("dir")),
+            ),
+            ("src/dir/sub.rs", &provider("dir_sub")),
+            // A `#[path]` file is treated like a `mod.rs` for its submodules.
+            (
+                "src/custom/renamed.rs",
+                &format!("pub mod sub;\n{}", provider("attributed")),
+            ),
+            ("src/custom/sub.rs", &provider("attributed_sub")),
+            // Inline modules map to directories.
+            ("src/inline/nested.rs", &provider("nested")),
+            ("src/inline/other.rs", &provider("attributed_in_inline")),
+            // Not declared as a module, so never scanned.
+            ("src/orphan.rs", &provider("orphan")),
+        ],
+    );
+    let scan = wire_build::scan_target(&fixture.path("src/lib.rs"), &fixture.cache_path()).unwrap();
+    let mut paths: Vec<String> = scan
+        .cache
+        .values()
+        .flat_map(|r| r.providers.iter().map(|p| p.path.clone()))
+        .collect();
+    paths.sort();
+    assert_eq!(
+        paths,
+        [
+            "crate::attributed::attributed",
+            "crate::attributed::sub::attributed_sub",
+            "crate::dir::dir",
+            "crate::dir::sub::dir_sub",
+            "crate::file::file",
+            "crate::file::sub::file_sub",
+            "crate::inline::attributed_in_inline::attributed_in_inline",
+            "crate::inline::inline",
+            "crate::inline::nested::nested",
+        ]
+    );
+    let expected = format!(
+        "No file found for module `missing` (looked for {:?} and {:?})",
+        fixture.path("src/missing.rs"),
+        fixture.path("src/missing/mod.rs")
+    );
+    assert_eq!(scan.warnings, [expected]);
+}
+
+#[test]
+fn missing_module_file_fails_with_warnings_as_errors() {
+    let fixture = Fixture::new("modules-strict", &[("src/lib.rs", "pub mod missing;\n")]);
+    let error = wire_build::Builder::new()
+        .src(fixture.path("src"))
+        .warnings_as_errors(true)
+        .scan(&fixture.cache_path())
+        .unwrap_err();
+    assert!(
+        format!("{:#}", error).contains("No file found for module `missing`"),
+        "{:#}",
+        error
+    );
+}
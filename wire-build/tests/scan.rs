@@ -0,0 +1,64 @@
+//! Scanning of crates written to temporary directories.
+
+mod common;
+
+use common::Fixture;
+use wire_build::{ProviderInfo, ScanCache};
+
+fn providers(cache: &ScanCache) -> Vec<ProviderInfo> {
+    let mut providers: Vec<ProviderInfo> =
+        cache.values().flat_map(|r| r.providers.clone()).collect();
+    providers.sort_by(|a, b| a.path.cmp(&b.path));
+    providers
+}
+
+fn visibilities(cache: &ScanCache) -> Vec<(String, String)> {
+    providers(cache)
+        .into_iter()
+        .map(|p| (p.path, p.vis))
+        .collect()
+}
+
+#[test]
+fn providers_are_restricted_to_their_impl_type_in_another_file() {
+    let fixture = Fixture::new(
+        "impl-type-vis",
+        &[
+            ("src/lib.rs", "pub mod models;\npub mod services;\n"),
+            // Nothing in this file hints at wire, so only its types make it worth parsing.
+            ("src/models.rs", "pub(crate) struct Db;\npub struct Api;\n"),
+            (
+                "src/services.rs",
+                r#"
+                use crate::models::{Api, Db};
+                impl Db {
+                    #[wire::provider]
+                    pub fn connect() -> Db { Db }
+                }
+                impl Api {
+                    #[wire::provider]
+                    pub fn new() -> Api { Api }
+                }
+                #[wire::provider]
+                pub fn free() -> u8 { 0 }
+                "#,
+            ),
+        ],
+    );
+    let cache = wire_build::scan_target(&fixture.path("src/lib.rs"), &fixture.cache_path())
+        .unwrap()
+        .cache;
+    let expected = [
+        ("crate::models::Api::new", "pub"),
+        ("crate::models::Db::connect", "crate"),
+        ("crate::services::free", "pub"),
+    ]
+    .map(|(path, vis)| (path.to_string(), vis.to_string()));
+    assert_eq!(visibilities(&cache), expected);
+
+    // The restriction is not stored in the cache, so it also holds when loaded from it.
+    let cache = wire_build::scan_target(&fixture.path("src/lib.rs"), &fixture.cache_path())
+        .unwrap()
+        .cache;
+    assert_eq!(visibilities(&cache), expected);
+}
@@ -0,0 +1,109 @@
+//! Scanning of a fixture workspace through `cargo metadata`.
+
+mod common;
+
+use common::Fixture;
+use wire_build::workspace::{CrateManifest, scan_workspace};
+
+const PROVIDER: &str = "pub struct {ty};\n#[wire::provider]\npub fn {name}() -> {ty} { {ty} }\n";
+
+fn provider(name: &str, ty: &str) -> String {
+    PROVIDER.replace("{name}", name).replace("{ty}", ty)
+}
+
+fn package(name: &str, dependencies: &str) -> String {
+    format!(
+        "[package]\nname = \"{}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n{}",
+        name, dependencies
+    )
+}
+
+/// The provider paths seen by each crate of the workspace, by package, kind and target.
+fn providers(manifests: &[CrateManifest]) -> Vec<(String, Vec<String>)> {
+    let mut crates: Vec<(String, Vec<String>)> = manifests
+        .iter()
+        .map(|m| {
+            let mut paths: Vec<String> = m.providers.iter().map(|p| p.path.clone()).collect();
+            paths.sort();
+            (format!("{} {} {}", m.package, m.kind, m.target), paths)
+        })
+        .collect();
+    crates.sort();
+    crates
+}
+
+#[test]
+fn crates_see_the_libraries_they_can_use() {
+    let infra_lib = format!(
+        "{}pub(crate) struct Secret;\n#[wire::provider]\npub(crate) fn secret() -> Secret {{ Secret }}\n",
+        provider("db", "Db")
+    );
+    let fixture = Fixture::new(
+        "workspace",
+        &[
+            (
+                "Cargo.toml",
+                "[workspace]\nmembers = [\"infra\", \"app\", \"testkit\"]\nresolver = \"2\"\n",
+            ),
+            ("infra/Cargo.toml", &package("infra", "")),
+            ("infra/src/lib.rs", &infra_lib),
+            ("infra/src/main.rs", &provider("tool", "Tool")),
+            (
+                "app/Cargo.toml",
+                &package(
+                    "app",
+                    "[dependencies]\nstore = { package = \"infra\", path = \"../infra\" }\n\n\
+                     [dev-dependencies]\ntestkit = { path = \"../testkit\" }\n",
+                ),
+            ),
+            ("app/src/lib.rs", &provider("app", "App")),
+            ("app/tests/it.rs", &provider("fake", "Fake")),
+            ("testkit/Cargo.toml", &package("testkit", "")),
+            ("testkit/src/lib.rs", &provider("clock", "Clock")),
+        ],
+    );
+    let manifests = scan_workspace(&fixture.dir, &fixture.path("target/wire")).unwrap();
+
+    let expected = [
+        // Renamed dependencies are named after the rename, dev-dependencies are not used.
+        ("app lib app", vec!["::store::db", "crate::app"]),
+        // Tests also use dev-dependencies and their own package's library.
+        (
+            "app test it",
+            vec![
+                "::app::app",
+                "::store::db",
+                "::testkit::clock",
+                "crate::fake",
+            ],
+        ),
+        // Binaries use the public providers of their own package's library.
+        ("infra bin infra", vec!["::infra::db", "crate::tool"]),
+        ("infra lib infra", vec!["crate::db", "crate::secret"]),
+        ("testkit lib testkit", vec!["crate::clock"]),
+    ]
+    .map(|(name, paths)| {
+        (
+            name.to_string(),
+            paths.into_iter().map(String::from).collect::<Vec<_>>(),
+        )
+    });
+    assert_eq!(providers(&manifests), expected);
+}
+
+#[test]
+fn target_dir_follows_the_cargo_config_from_any_subdirectory() {
+    let fixture = Fixture::new(
+        "target-dir",
+        &[
+            ("Cargo.toml", &package("tool", "")),
+            ("src/lib.rs", ""),
+            (
+                ".cargo/config.toml",
+                "[build]\ntarget-dir = \"build-output\"\n",
+            ),
+        ],
+    );
+    let target_dir = wire_build::workspace::target_dir(&fixture.path("src")).unwrap();
+    assert_eq!(target_dir, fixture.path("build-output"));
+}
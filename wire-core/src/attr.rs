@@ -0,0 +1,290 @@
+use crate::types::split_result;
+use proc_macro2::{TokenStream, TokenTree};
+use syn::parse::{Parse, ParseStream};
+use syn::{
+    Attribute, FnArg, GenericArgument, GenericParam, Ident, ItemFn, LitStr, PathArguments,
+    ReturnType, Signature, Token, Type,
+};
+
+/// Wrappers stripped during type normalization unless `#[wire(wrappers = [...])]` overrides them.
+pub const DEFAULT_WRAPPERS: &[&str] = &["Arc", "Box", "Rc"];
+
+/// Default name of the provider metadata file inside `OUT_DIR`.
+pub const DEFAULT_PROVIDERS_FILE: &str = "providers.json";
+
+// Simple struct to parse macro attributes like #[wire(wrappers = ["Arc", "Box"], debug)]
+pub struct WireAttr {
+    pub wrappers: Vec<String>,
+    pub file: String,
+    /// Write the pretty-printed expansion to `OUT_DIR/wire_expanded/<path>.rs`, named
+    /// after the injector's path with `::` replaced by `__`.
+    pub debug: bool,
+    /// Name of an error type to generate next to the injector, which wraps provider
+    /// errors with the failed provider and its dependency chain.
+    pub error: Option<Ident>,
+}
+
+impl Default for WireAttr {
+    fn default() -> Self {
+        WireAttr {
+            wrappers: DEFAULT_WRAPPERS.iter().map(|w| w.to_string()).collect(),
+            file: DEFAULT_PROVIDERS_FILE.to_string(),
+            debug: false,
+            error: None,
+        }
+    }
+}
+
+impl Parse for WireAttr {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut attr = WireAttr::default();
+
+        while !input.is_empty() {
+            let ident: Ident = input.parse()?;
+            if ident == "wrappers" {
+                input.parse::<Token![=]>()?;
+                let content;
+                syn::bracketed!(content in input);
+                let lit_strs: syn::punctuated::Punctuated<LitStr, Token![,]> =
+                    content.parse_terminated(|i| i.parse(), Token![,])?;
+                attr.wrappers = lit_strs.into_iter().map(|s| s.value()).collect();
+            } else if ident == "file" {
+                input.parse::<Token![=]>()?;
+                let s: LitStr = input.parse()?;
+                attr.file = s.value();
+            } else if ident == "debug" {
+                attr.debug = true;
+            } else if ident == "error" {
+                input.parse::<Token![=]>()?;
+                attr.error = Some(input.parse()?);
+            }
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+
+        Ok(attr)
+    }
+}
+
+/// Arguments of `#[provider(...)]`, e.g. `#[provider(map_err = MyError::Db)]`.
+#[derive(Default)]
+pub struct ProviderAttr {
+    /// Function or closure applied to the provider's error before it is propagated.
+    pub map_err: Option<syn::Expr>,
+}
+
+impl Parse for ProviderAttr {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut attr = ProviderAttr::default();
+
+        while !input.is_empty() {
+            let ident: Ident = input.parse()?;
+            if ident == "map_err" {
+                input.parse::<Token![=]>()?;
+                attr.map_err = Some(input.parse()?);
+            } else {
+                return Err(syn::Error::new(
+                    ident.span(),
+                    "unknown provider option, expected `map_err = ...`",
+                ));
+            }
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+
+        Ok(attr)
+    }
+}
+
+/// Parses a provider override on a parameter, `#[inject(Type)]` or `#[wire(from = "Type")]`.
+/// Returns `Ok(None)` for other attributes and an error for malformed ones, which
+/// would otherwise silently fall back to the parameter's own type.
+pub fn parse_inject_attr(attr: &Attribute) -> syn::Result<Option<Type>> {
+    if attr.path().is_ident("inject") {
+        return attr.parse_args::<Type>().map(Some);
+    }
+    if !attr.path().is_ident("wire") {
+        return Ok(None);
+    }
+
+    // Options are parsed by hand rather than as `Meta`, whose values are expressions:
+    // an unquoted `from = Arc<dyn Repo>` would fail as a chained comparison.
+    let from = attr.parse_args_with(|input: ParseStream| {
+        let mut from = None;
+        while !input.is_empty() {
+            let ident: Ident = input.parse()?;
+            if ident != "from" || !input.peek(Token![=]) {
+                return Err(syn::Error::new(
+                    ident.span(),
+                    "unknown parameter option, expected `from = \"Type\"`",
+                ));
+            }
+            let eq: Token![=] = input.parse()?;
+            let mut value = TokenStream::new();
+            while !input.is_empty() && !input.peek(Token![,]) {
+                value.extend([input.parse::<TokenTree>()?]);
+            }
+            let lit: LitStr = syn::parse2(value.clone()).map_err(|_| {
+                let message = "expected a string literal, e.g. `from = \"Arc<dyn Repo>\"`";
+                if value.is_empty() {
+                    syn::Error::new(eq.span, message)
+                } else {
+                    syn::Error::new_spanned(&value, message)
+                }
+            })?;
+            from = Some(lit.parse::<Type>()?);
+            if !input.is_empty() {
+                input.parse::<Token![,]>()?;
+            }
+        }
+        Ok(from)
+    })?;
+    match from {
+        Some(ty) => Ok(Some(ty)),
+        None => Err(syn::Error::new_spanned(
+            attr,
+            "expected `#[wire(from = \"Type\")]`",
+        )),
+    }
+}
+
+/// Parses the type of a `#[bind(Type)]` attribute, or returns `Ok(None)` for other attributes.
+pub fn parse_bind_attr(attr: &Attribute) -> syn::Result<Option<Type>> {
+    if !attr.path().is_ident("bind") {
+        return Ok(None);
+    }
+    attr.parse_args::<Type>().map(Some)
+}
+
+/// Checks everything `#[provider]` rejects: its signature, a `map_err` on a provider
+/// that does not return a `Result`, and malformed `#[bind]`, `#[inject]` and
+/// `#[wire(from)]` attributes, the same way `wire-build` reads them.
+pub fn validate_provider(attr: &ProviderAttr, func: &ItemFn) -> syn::Result<()> {
+    let mut errors = Vec::new();
+    errors.extend(validate_provider_signature(&func.sig).err());
+    if let Some(map_err) = &attr.map_err {
+        let is_result = matches!(&func.sig.output, ReturnType::Type(_, ty) if split_result(ty).1);
+        if !is_result {
+            errors.push(syn::Error::new_spanned(
+                map_err,
+                "`map_err` requires the provider to return a `Result`",
+            ));
+        }
+    }
+    errors.extend(
+        func.attrs
+            .iter()
+            .filter_map(|attr| parse_bind_attr(attr).err()),
+    );
+    for input in &func.sig.inputs {
+        if let FnArg::Typed(pat_type) = input {
+            errors.extend(
+                pat_type
+                    .attrs
+                    .iter()
+                    .filter_map(|attr| parse_inject_attr(attr).err()),
+            );
+        }
+    }
+    combine(errors)
+}
+
+/// Checks that a `#[provider]` signature can be called by generated injectors: it
+/// must be a synchronous function without type or const parameters that returns a
+/// concrete value, and take neither `self` nor `impl Trait` parameters.
+/// Every violation is reported at its own span.
+pub fn validate_provider_signature(sig: &Signature) -> syn::Result<()> {
+    let mut errors = Vec::new();
+
+    if let Some(asyncness) = &sig.asyncness {
+        errors.push(syn::Error::new_spanned(
+            asyncness,
+            "providers cannot be `async`, as injectors call them synchronously; provide the value the future resolves to",
+        ));
+    }
+    for param in &sig.generics.params {
+        if !matches!(param, GenericParam::Lifetime(_)) {
+            errors.push(syn::Error::new_spanned(
+                param,
+                "providers cannot have type or const parameters, as injectors need the concrete type they provide",
+            ));
+        }
+    }
+
+    match &sig.output {
+        ReturnType::Default => errors.push(syn::Error::new_spanned(
+            &sig.ident,
+            "providers must return the type they provide, but this function returns `()`",
+        )),
+        ReturnType::Type(_, ty) if matches!(&**ty, Type::Tuple(tuple) if tuple.elems.is_empty()) => {
+            errors.push(syn::Error::new_spanned(
+                ty,
+                "providers must return the type they provide, not `()`",
+            ))
+        }
+        ReturnType::Type(_, ty) => {
+            if let Some(impl_trait) = find_impl_trait(ty) {
+                errors.push(syn::Error::new_spanned(
+                    impl_trait,
+                    "providers cannot return `impl Trait`; return a concrete type or a `Box<dyn Trait>`",
+                ));
+            }
+        }
+    }
+
+    for input in &sig.inputs {
+        match input {
+            FnArg::Receiver(receiver) => errors.push(syn::Error::new_spanned(
+                receiver,
+                "providers cannot take `self`; make this an associated function without a receiver",
+            )),
+            FnArg::Typed(pat_type) => {
+                if let Some(impl_trait) = find_impl_trait(&pat_type.ty) {
+                    errors.push(syn::Error::new_spanned(
+                        impl_trait,
+                        "providers cannot take `impl Trait` parameters; use a concrete type or `&dyn Trait`",
+                    ));
+                }
+            }
+        }
+    }
+
+    combine(errors)
+}
+
+/// Combines errors into one, which reports each at its own span.
+fn combine(errors: Vec<syn::Error>) -> syn::Result<()> {
+    let mut errors = errors.into_iter();
+    match errors.next() {
+        Some(mut first) => {
+            errors.for_each(|e| first.combine(e));
+            Err(first)
+        }
+        None => Ok(()),
+    }
+}
+
+/// Finds an `impl Trait` in a type, looking through references, groups and the
+/// type arguments of paths, e.g. in `Result<impl Repo, Error>`.
+fn find_impl_trait(ty: &Type) -> Option<&Type> {
+    match ty {
+        Type::ImplTrait(_) => Some(ty),
+        Type::Reference(reference) => find_impl_trait(&reference.elem),
+        Type::Paren(paren) => find_impl_trait(&paren.elem),
+        Type::Group(group) => find_impl_trait(&group.elem),
+        Type::Path(type_path) => type_path
+            .path
+            .segments
+            .iter()
+            .find_map(|segment| match &segment.arguments {
+                PathArguments::AngleBracketed(args) => args.args.iter().find_map(|arg| match arg {
+                    GenericArgument::Type(ty) => find_impl_trait(ty),
+                    _ => None,
+                }),
+                _ => None,
+            }),
+        _ => None,
+    }
+}
@@ -0,0 +1,860 @@
+//! Generates the body of a `#[wire]` injector from the resolved provider graph.
+//!
+//! This lives outside the proc-macro crate so that tools such as `cargo wire expand`
+//! can produce exactly the same code as the macro.
+
+use crate::graph::{is_match, normalize_type, Graph};
+use crate::models::ProviderInfo;
+use crate::types::{argument_name, display_type, split_output, strip_option};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote, ToTokens};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use syn::{FnArg, Ident, ItemFn, Path, ReturnType, Signature, Visibility};
+
+/// Expands an injector with the given visibility and signature into a complete
+/// function definition that calls every required provider in dependency order.
+/// With an `error` type name (`#[wire(error = ...)]`), the definition of that type
+/// is generated as well and wraps every provider error.
+pub fn expand(
+    vis: &Visibility,
+    sig: &Signature,
+    wrappers: &[String],
+    error: Option<&Ident>,
+    providers: &[ProviderInfo],
+) -> Result<TokenStream, String> {
+    let graph = Graph::new(providers, wrappers.to_vec())?;
+    expand_with_graph(vis, sig, wrappers, error, &graph)
+}
+
+/// Like [`expand`], but with a graph that was already built from the providers
+/// with the same `wrappers`, so it can be shared between injectors.
+pub fn expand_with_graph(
+    vis: &Visibility,
+    sig: &Signature,
+    wrappers: &[String],
+    error: Option<&Ident>,
+    graph: &Graph,
+) -> Result<TokenStream, String> {
+    // 1. Parse target type from function signature
+    let (target_ty, is_target_result, is_target_option) = match &sig.output {
+        ReturnType::Type(_, ty) => split_output(ty),
+        ReturnType::Default => {
+            return Err("'#[wire]' function must have a return type.".to_string());
+        }
+    };
+
+    let normalized_target = normalize_type(&target_ty, wrappers);
+    let error_handling = match (&sig.output, error) {
+        (_, Some(_)) if !is_target_result => {
+            return Err("'#[wire(error = ...)]' requires the function to return a Result of that error type.".to_string());
+        }
+        (_, Some(error)) => ErrorHandling::Wire(error.clone()),
+        (ReturnType::Type(_, ty), None) if is_target_result => ErrorHandling::of(ty),
+        _ => ErrorHandling::Propagate,
+    };
+
+    // 2. Resolve the dependency graph
+    let target_key = graph
+        .lookup(&normalized_target)
+        .map_or(normalized_target.clone(), str::to_string);
+
+    let sorted_providers = graph.resolve(&target_key)?;
+
+    // 3. Generate the function body
+    let mut var_map: HashMap<String, Ident> = HashMap::new();
+    let mut actual_type_map: HashMap<String, String> = HashMap::new(); // Store original return type
+    let mut generated_body = Vec::new();
+    let mut error_checks = Vec::new();
+    // Keys whose variable is an `Option`, because their provider may produce nothing.
+    let mut optional_vars: HashSet<String> = HashSet::new();
+
+    let chain_to = |key: &str| -> Vec<String> {
+        graph
+            .dependency_chain(&target_key, key)
+            .unwrap_or_else(|| vec![key.to_string()])
+            .iter()
+            .map(|key| display_key(graph, key, wrappers))
+            .collect()
+    };
+    // How the injector returns early when the optional provider of `key` produced nothing.
+    let none_return = |key: &str| -> Result<TokenStream, String> {
+        let provider = &graph.nodes[key].provider;
+        if is_target_option {
+            return Ok(if is_target_result {
+                quote! { return Ok(None) }
+            } else {
+                quote! { return None }
+            });
+        }
+        let ty = display_key(graph, key, wrappers);
+        if let ErrorHandling::Wire(error) = &error_handling {
+            let path = &provider.path;
+            let chain = chain_to(key);
+            let message = format!("{} provided no {}", path, ty);
+            return Ok(quote! {
+                return Err(#error {
+                    provider: #path,
+                    chain: &[#(#chain),*],
+                    source: ::core::convert::Into::into(#message),
+                })
+            });
+        }
+        Err(format!(
+            "`{}` may not provide `{}` because it returns an Option. Depend on `Option<&{}>` instead, \
+             make the injector return an Option, or use `#[wire(error = ...)]`.",
+            provider.path, ty, ty
+        ))
+    };
+
+    let usage = Usage::analyze(&sorted_providers, &target_key, graph, wrappers)?;
+    let mut remaining = usage.uses.clone();
+    // Counts a use of the variable of `key` and returns whether it was the last one,
+    // in which case the value can be moved instead of cloned.
+    let mut take_use = |key: &str| -> bool {
+        let uses = remaining.get_mut(key).expect("BUG: use not counted");
+        *uses -= 1;
+        *uses == 0
+    };
+    // Keys whose values are cloned, each getting a `Clone` check with its consumers.
+    let mut cloned: BTreeSet<String> = BTreeSet::new();
+    let mut owned = |key: &str, place: TokenStream, movable: bool| -> TokenStream {
+        if movable {
+            return place;
+        }
+        cloned.insert(key.to_string());
+        let clone_trait = clone_trait_name(key);
+        quote! { #clone_trait::wire_clone(&#place) }
+    };
+
+    for (provider, arg_keys) in sorted_providers.iter().zip(&usage.arg_keys) {
+        let ret_ty_normalized = normalize_type(&provider.ret, wrappers);
+
+        let var_base = provider
+            .ret
+            .split('<')
+            .next()
+            .unwrap()
+            .trim()
+            .split("::")
+            .last()
+            .unwrap()
+            .chars()
+            .filter(|c| c.is_alphanumeric() || *c == '_')
+            .collect::<String>()
+            .to_lowercase();
+
+        let var_name = format_ident!("{}_{}", var_base, var_map.len());
+        var_map.insert(ret_ty_normalized.clone(), var_name.clone());
+        actual_type_map.insert(ret_ty_normalized.clone(), provider.ret.clone());
+
+        let provider_path: Path = syn::parse_str(&provider.path).unwrap();
+
+        let mut arg_tokens = Vec::new();
+        for (arg, arg_key) in provider.args.iter().zip(arg_keys) {
+            let arg_var = var_map
+                .get(arg_key)
+                .unwrap_or_else(|| panic!("BUG: Dependency '{}' not found in var_map", arg_key));
+
+            // An `Option<...>` argument is adapted like the type inside it.
+            let optional_arg_ty = strip_option(&arg.ty);
+            let arg_ty: syn::Type = syn::parse_str(optional_arg_ty.as_deref().unwrap_or(&arg.ty))
+                .map_err(|e| {
+                format!(
+                    "Invalid type of argument `{}` of provider {}: {}",
+                    arg.name, provider.path, e
+                )
+            })?;
+            let (is_arg_ref, is_arg_mut, expected_ty) = match arg_ty {
+                syn::Type::Reference(reference) => {
+                    (true, reference.mutability.is_some(), *reference.elem)
+                }
+                ty => (false, false, ty),
+            };
+            let provided_ty: syn::Type =
+                syn::parse_str(&actual_type_map[arg_key]).map_err(|e| {
+                    format!(
+                        "Invalid type provided for {}: {}",
+                        display_type(&actual_type_map[arg_key]),
+                        e
+                    )
+                })?;
+            let adaptation = Adaptation::of(&provided_ty, &expected_ty, wrappers);
+            let bridge_name = format_ident!("{}_bridge_{}", arg_var, arg_tokens.len());
+            let bridge_mut = if is_arg_mut {
+                quote! { mut }
+            } else {
+                quote! {}
+            };
+
+            // The last use of a variable moves it; only arguments taken by value need to.
+            // A value that is also passed to another argument of the same call cannot be
+            // moved while that argument borrows it.
+            let shared_in_call = arg_keys.iter().filter(|key| *key == arg_key).count() > 1;
+            let last = take_use(arg_key) && !shared_in_call;
+            let by_value = !is_arg_ref || matches!(adaptation, Adaptation::Bridge { .. });
+            let matched = match (last && by_value, is_arg_mut) {
+                (true, _) => quote! { #arg_var },
+                (false, true) => quote! { &mut #arg_var },
+                (false, false) => quote! { &#arg_var },
+            };
+            // Inside `match &var { Some(value) => ... }`, the value is behind a reference.
+            let matched_value = if last && by_value {
+                quote! { value }
+            } else {
+                quote! { (*value) }
+            };
+
+            let optional_dep = optional_vars.contains(arg_key);
+            if optional_dep && optional_arg_ty.is_some() {
+                // Both sides are optional: pass the value on, or `None`.
+                if let Adaptation::Bridge { ty, wrap } = &adaptation {
+                    let value = owned(arg_key, matched_value, last);
+                    let value = bridged(ty, wrap.as_ref(), value);
+                    generated_body.push(quote! {
+                        let #bridge_mut #bridge_name: Option<#ty> = match #matched {
+                            Some(value) => Some(#value),
+                            None => None,
+                        };
+                    });
+                    arg_tokens.push(match (is_arg_ref, is_arg_mut) {
+                        (_, true) => quote! { #bridge_name.as_mut() },
+                        (true, false) => quote! { #bridge_name.as_ref() },
+                        (false, false) => quote! { #bridge_name },
+                    });
+                } else {
+                    let value = adapt_arg(
+                        matched_value,
+                        last && by_value,
+                        is_arg_ref,
+                        is_arg_mut,
+                        &adaptation,
+                        |place, movable| owned(arg_key, place, movable),
+                    );
+                    arg_tokens.push(
+                        quote! { match #matched { Some(value) => Some(#value), None => None } },
+                    );
+                }
+                continue;
+            }
+
+            let (mut source, mut movable) = (quote! { #arg_var }, last);
+            if optional_dep {
+                // The argument needs a value: stop if the provider produced nothing.
+                let none = none_return(arg_key)?;
+                let some_name = format_ident!("{}_some_{}", arg_var, arg_tokens.len());
+                generated_body.push(quote! {
+                    let #some_name = match #matched {
+                        Some(value) => value,
+                        None => #none,
+                    };
+                });
+                (source, movable) = if last && by_value {
+                    (quote! { #some_name }, true)
+                } else {
+                    (quote! { (*#some_name) }, false)
+                };
+            }
+            if let Adaptation::Bridge { ty, wrap } = &adaptation {
+                let value = owned(arg_key, source, movable);
+                let value = bridged(ty, wrap.as_ref(), value);
+                generated_body.push(quote! {
+                    let #bridge_mut #bridge_name: #ty = #value;
+                });
+                (source, movable) = (quote! { #bridge_name }, true);
+            }
+
+            let value = adapt_arg(
+                source,
+                movable,
+                is_arg_ref,
+                is_arg_mut,
+                &adaptation,
+                |place, movable| owned(arg_key, place, movable),
+            );
+            arg_tokens.push(if optional_arg_ty.is_some() {
+                quote! { Some(#value) }
+            } else {
+                value
+            });
+        }
+
+        let mut value = quote! { #provider_path(#(#arg_tokens),*) };
+        if provider.is_result {
+            if !is_target_result {
+                return Err(
+                    "Target function must return Result because some providers return Result."
+                        .to_string(),
+                );
+            }
+            if let Some(map_err) = &provider.map_err {
+                let map_err: syn::Expr = syn::parse_str(map_err)
+                    .map_err(|e| format!("Invalid map_err of provider {}: {}", provider.path, e))?;
+                value = quote! { #value.map_err(#map_err) };
+            }
+            let context = format!("while building {}", display_type(&provider.ret));
+            value = match &error_handling {
+                ErrorHandling::Convert(error_ty) => {
+                    // A function per provider, so that a missing `From` impl names the provider.
+                    let check = format_ident!(
+                        "__wire_convert_error_of_{}",
+                        provider.path.replace("::", "_")
+                    );
+                    error_checks.push(quote! {
+                        #[allow(non_snake_case)]
+                        fn #check<T, E>(result: ::core::result::Result<T, E>) -> ::core::result::Result<T, #error_ty>
+                        where
+                            E: ::core::convert::Into<#error_ty>,
+                        {
+                            result.map_err(::core::convert::Into::into)
+                        }
+                    });
+                    quote! { #check(#value)? }
+                }
+                ErrorHandling::Anyhow => quote! { ::anyhow::Context::context(#value, #context)? },
+                ErrorHandling::Eyre(eyre) => quote! { #eyre::WrapErr::wrap_err(#value, #context)? },
+                ErrorHandling::Wire(error) => {
+                    let path = &provider.path;
+                    let chain = chain_to(&ret_ty_normalized);
+                    quote! {
+                        #value.map_err(|source| #error {
+                            provider: #path,
+                            chain: &[#(#chain),*],
+                            source: ::core::convert::Into::into(source),
+                        })?
+                    }
+                }
+                ErrorHandling::Propagate => quote! { #value? },
+            };
+        }
+
+        let var_mut = if usage.mutable.contains(&ret_ty_normalized) {
+            quote! { mut }
+        } else {
+            quote! {}
+        };
+        generated_body.push(quote! {
+            let #var_mut #var_name = #value;
+        });
+        if provider.is_option {
+            optional_vars.insert(ret_ty_normalized.clone());
+        }
+
+        for b in &provider.bindings {
+            let ty_b_normalized = normalize_type(b, wrappers);
+            let b_type: syn::Type = syn::parse_str(b).unwrap();
+            let var_name_binding =
+                format_ident!("{}_as_{}", var_base, ident_part(&ty_b_normalized));
+
+            // Generate a bridging variable to trigger coercion, cast like a bridge (see `bridged`)
+            let last = take_use(&ret_ty_normalized);
+            let binding_mut = if usage.mutable.contains(&ty_b_normalized) {
+                quote! { mut }
+            } else {
+                quote! {}
+            };
+            if provider.is_option {
+                let (matched, value) = if last {
+                    (quote! { #var_name }, quote! { value })
+                } else {
+                    (quote! { &#var_name }, quote! { (*value) })
+                };
+                let value = owned(&ret_ty_normalized, value, last);
+                generated_body.push(quote! {
+                    let #binding_mut #var_name_binding: Option<#b_type> = match #matched {
+                        Some(value) => Some(#value as #b_type),
+                        None => None,
+                    };
+                });
+                optional_vars.insert(ty_b_normalized.clone());
+            } else {
+                let value = owned(&ret_ty_normalized, quote! { #var_name }, last);
+                generated_body.push(quote! {
+                    let #binding_mut #var_name_binding: #b_type = #value as #b_type;
+                });
+            }
+
+            var_map.insert(ty_b_normalized.clone(), var_name_binding);
+            actual_type_map.insert(ty_b_normalized, b.to_string());
+        }
+    }
+
+    let final_var = var_map
+        .get(&target_key)
+        .expect("BUG: Final target not in var_map");
+    take_use(&target_key);
+    let clone_checks: Vec<TokenStream> = cloned
+        .iter()
+        .map(|key| clone_trait(key, &usage.consumers[key]))
+        .collect();
+
+    let mut final_value = quote! { #final_var };
+    match (optional_vars.contains(&target_key), is_target_option) {
+        (false, true) => final_value = quote! { Some(#final_var) },
+        (true, false) => {
+            let none = none_return(&target_key)?;
+            generated_body.push(quote! {
+                let Some(#final_var) = #final_var else { #none };
+            });
+        }
+        _ => {}
+    }
+
+    let final_return = if is_target_result {
+        quote! { Ok(#final_value) }
+    } else {
+        final_value
+    };
+
+    let error_type = error.map(|error| error_type(vis, error));
+    let expanded = quote! {
+        #error_type
+        #vis #sig {
+            #(#error_checks)*
+            #(#clone_checks)*
+            #(#generated_body)*
+            #final_return
+        }
+    };
+
+    Ok(expanded)
+}
+
+/// How an injector returning a `Result` propagates the errors of its providers.
+///
+/// Only the injector's signature is known here, not the `use` items of its module,
+/// so `anyhow` and `eyre` are recognized when the return type names their crate:
+/// after `use anyhow::Result;`, `Result<T>` is [`ErrorHandling::Propagate`]d without
+/// context.
+enum ErrorHandling {
+    /// `Result<T, E>`: provider errors are converted into `E` with `From`.
+    Convert(Box<syn::Type>),
+    /// `anyhow::Result<T>`: provider errors get the type being built as context.
+    Anyhow,
+    /// `eyre::Result<T>`, like [`ErrorHandling::Anyhow`] with `WrapErr`. Holds the
+    /// path of the `eyre` crate, which `color_eyre` re-exports.
+    Eyre(TokenStream),
+    /// `#[wire(error = ...)]`: provider errors are wrapped in the generated error type.
+    Wire(Ident),
+    /// A `Result` alias that does not name its error type: errors are propagated with `?`.
+    Propagate,
+}
+
+impl ErrorHandling {
+    fn of(ty: &syn::Type) -> Self {
+        let syn::Type::Path(type_path) = ty else {
+            return ErrorHandling::Propagate;
+        };
+        if let Some(handling) = Self::of_crate(&type_path.path) {
+            return handling;
+        }
+        let Some(last) = type_path.path.segments.last() else {
+            return ErrorHandling::Propagate;
+        };
+        let syn::PathArguments::AngleBracketed(args) = &last.arguments else {
+            return ErrorHandling::Propagate;
+        };
+        match args.args.iter().nth(1) {
+            Some(syn::GenericArgument::Type(error_ty)) => match error_ty {
+                syn::Type::Path(error_path) => Self::of_crate(&error_path.path),
+                _ => None,
+            }
+            .unwrap_or_else(|| ErrorHandling::Convert(Box::new(error_ty.clone()))),
+            _ => ErrorHandling::Propagate,
+        }
+    }
+
+    /// Recognizes `anyhow::Result`, `anyhow::Error`, `eyre::Report` and the like by their crate.
+    fn of_crate(path: &Path) -> Option<Self> {
+        let first = path.segments.first()?.ident.to_string();
+        match first.as_str() {
+            _ if path.segments.len() < 2 => None,
+            "anyhow" => Some(ErrorHandling::Anyhow),
+            "eyre" => Some(ErrorHandling::Eyre(quote! { ::eyre })),
+            "color_eyre" => Some(ErrorHandling::Eyre(quote! { ::color_eyre::eyre })),
+            _ => None,
+        }
+    }
+}
+
+/// Passes `source`, a place of the provider's type (or of the bridge built for the
+/// argument), as an argument that is a shared or mutable reference or owned,
+/// dereferencing smart pointers for [`Adaptation::Deref`]. Owned values are moved if
+/// `movable`, and cloned through `owned` otherwise.
+fn adapt_arg(
+    source: TokenStream,
+    movable: bool,
+    is_arg_ref: bool,
+    is_arg_mut: bool,
+    adaptation: &Adaptation,
+    mut owned: impl FnMut(TokenStream, bool) -> TokenStream,
+) -> TokenStream {
+    let deref = match adaptation {
+        Adaptation::Deref { boxed } => Some(*boxed),
+        Adaptation::Direct | Adaptation::Bridge { .. } => None,
+    };
+    match (is_arg_ref, is_arg_mut, deref) {
+        (_, true, Some(_)) => quote! { &mut *#source },
+        (_, true, None) => quote! { &mut #source },
+        (true, false, Some(_)) => quote! { &*#source },
+        (true, false, None) => quote! { &#source },
+        // Only a `Box` can be moved out of; other smart pointers may be shared.
+        (false, _, Some(true)) if movable => quote! { *#source },
+        (false, _, Some(_)) => owned(quote! { (*#source) }, false),
+        (false, _, None) => owned(source, movable),
+    }
+}
+
+/// How the value of a provider is passed to an argument of another type.
+#[derive(Debug)]
+enum Adaptation {
+    /// The argument takes the provided type, e.g. `Db`, `&Db` or `&dyn Repo` from `Db`.
+    Direct,
+    /// The argument takes what the provided smart pointer points to, e.g. `&Db`,
+    /// `&dyn Repo` or a clone of `Db` from `Arc<Db>`, and `Db` moved out of `Box<Db>`.
+    Deref { boxed: bool },
+    /// The argument takes another smart pointer, which is built in a local variable of
+    /// type `ty`: by unsized coercion for `Arc<dyn Repo>` from `Arc<SqlRepo>`, after
+    /// `wrap` such as `Arc::new` for `Arc<Db>` from `Db` or `Arc::from` for `Arc<Db>`
+    /// from `Box<Db>`.
+    Bridge {
+        ty: TokenStream,
+        wrap: Option<TokenStream>,
+    },
+}
+
+impl Adaptation {
+    /// The adaptation of a value of type `provided` to an argument of type `arg`,
+    /// without the argument's reference. Types that do not fit any adaptation are
+    /// passed directly, so that rustc reports the mismatch.
+    fn of(provided: &syn::Type, arg: &syn::Type, wrappers: &[String]) -> Self {
+        if same_type(provided, arg) {
+            return Adaptation::Direct;
+        }
+        let provided_pointer = Pointer::of(provided, wrappers);
+        if let Some(pointer) = &provided_pointer {
+            if same_type(&pointer.inner, arg) || is_trait_object(arg) {
+                return Adaptation::Deref {
+                    boxed: pointer.boxed,
+                };
+            }
+        }
+        let Some(arg_pointer) = Pointer::of(arg, wrappers) else {
+            return Adaptation::Direct;
+        };
+        let (path, inner) = (&arg_pointer.path, &arg_pointer.inner);
+        let ty = quote! { #path<#inner> };
+        match provided_pointer {
+            Some(pointer) if pointer.path.to_string() == path.to_string() => {
+                Adaptation::Bridge { ty, wrap: None }
+            }
+            Some(pointer) if pointer.boxed => Adaptation::Bridge {
+                ty,
+                wrap: Some(quote! { #path::from }),
+            },
+            Some(_) => Adaptation::Direct,
+            None => Adaptation::Bridge {
+                ty,
+                wrap: Some(quote! { #path::new }),
+            },
+        }
+    }
+}
+
+/// The value of an [`Adaptation::Bridge`] of type `ty`. A coerced value is cast so
+/// that the bridge's type does not leak into the inference of a clone.
+fn bridged(ty: &TokenStream, wrap: Option<&TokenStream>, value: TokenStream) -> TokenStream {
+    match wrap {
+        Some(wrap) => quote! { #wrap(#value) },
+        None => quote! { #value as #ty },
+    }
+}
+
+/// A type wrapped in one of the injector's smart pointers, e.g. `Arc<Db>`.
+struct Pointer {
+    /// Path of the pointer without its type argument, fully qualified for the ones
+    /// in `std` so that generated code does not depend on the injector's imports.
+    path: TokenStream,
+    inner: syn::Type,
+    boxed: bool,
+}
+
+impl Pointer {
+    fn of(ty: &syn::Type, wrappers: &[String]) -> Option<Self> {
+        let syn::Type::Path(type_path) = ty else {
+            return None;
+        };
+        let last = type_path.path.segments.last()?;
+        let name = last.ident.to_string();
+        if type_path.qself.is_some()
+            || !wrappers
+                .iter()
+                .any(|w| w.rsplit("::").next() == Some(&name))
+        {
+            return None;
+        }
+        let syn::PathArguments::AngleBracketed(args) = &last.arguments else {
+            return None;
+        };
+        let inner = match args.args.first() {
+            Some(syn::GenericArgument::Type(inner)) if args.args.len() == 1 => inner.clone(),
+            _ => return None,
+        };
+
+        let first = type_path.path.segments.first()?.ident.to_string();
+        let in_std =
+            type_path.path.segments.len() == 1 || matches!(first.as_str(), "std" | "alloc");
+        let path = match name.as_str() {
+            "Arc" if in_std => quote! { ::std::sync::Arc },
+            "Rc" if in_std => quote! { ::std::rc::Rc },
+            "Box" if in_std => quote! { ::std::boxed::Box },
+            _ => {
+                let mut path = type_path.path.clone();
+                path.segments.last_mut()?.arguments = syn::PathArguments::None;
+                quote! { #path }
+            }
+        };
+        Some(Pointer {
+            path,
+            inner,
+            boxed: name == "Box" && in_std,
+        })
+    }
+}
+
+/// Whether two types are the same, allowing for different path prefixes.
+fn same_type(a: &syn::Type, b: &syn::Type) -> bool {
+    let a = normalize_type(&a.to_token_stream().to_string(), &[]);
+    let b = normalize_type(&b.to_token_stream().to_string(), &[]);
+    is_match(&a, &b) || is_match(&b, &a)
+}
+
+fn is_trait_object(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::TraitObject(_) => true,
+        syn::Type::Paren(paren) => is_trait_object(&paren.elem),
+        syn::Type::Group(group) => is_trait_object(&group.elem),
+        _ => false,
+    }
+}
+
+/// How the variables of an injector body are used, so that each value is moved into
+/// its last consumer and only cloned for the others.
+struct Usage {
+    /// The key of the dependency of every argument, per provider in resolution order.
+    arg_keys: Vec<Vec<String>>,
+    /// Number of uses of the variable of each key, including bindings and the injector's return.
+    uses: HashMap<String, usize>,
+    /// What uses each key, each listed once, for diagnostics.
+    consumers: HashMap<String, Vec<String>>,
+    /// Keys borrowed mutably, whose variables are declared `mut`.
+    mutable: HashSet<String>,
+}
+
+impl Usage {
+    fn analyze(
+        providers: &[ProviderInfo],
+        target_key: &str,
+        graph: &Graph,
+        wrappers: &[String],
+    ) -> Result<Self, String> {
+        let mut usage = Usage {
+            arg_keys: Vec::new(),
+            uses: HashMap::new(),
+            consumers: HashMap::new(),
+            mutable: HashSet::new(),
+        };
+        // Keys in the order their variables are defined, matched like `Graph::lookup`.
+        let mut keys: Vec<String> = Vec::new();
+        let add_use = |usage: &mut Usage, key: &str, consumer: String| {
+            *usage.uses.entry(key.to_string()).or_default() += 1;
+            let consumers = usage.consumers.entry(key.to_string()).or_default();
+            if !consumers.contains(&consumer) {
+                consumers.push(consumer);
+            }
+        };
+
+        for provider in providers {
+            let key = normalize_type(&provider.ret, wrappers);
+            keys.push(key.clone());
+
+            let mut arg_keys = Vec::new();
+            for arg in &provider.args {
+                let ty = normalize_type(&arg.lookup_type(), wrappers);
+                let arg_key = keys
+                    .iter()
+                    .find(|k| **k == ty)
+                    .or_else(|| keys.iter().find(|k| is_match(&ty, k) || is_match(k, &ty)))
+                    .cloned()
+                    .unwrap_or(ty);
+                let arg_ty = strip_option(&arg.ty).unwrap_or_else(|| arg.ty.clone());
+                if matches!(syn::parse_str::<syn::Type>(&arg_ty), Ok(syn::Type::Reference(r)) if r.mutability.is_some())
+                {
+                    usage.mutable.insert(arg_key.clone());
+                }
+                add_use(&mut usage, &arg_key, format!("`{}`", provider.path));
+                arg_keys.push(arg_key);
+            }
+            usage.arg_keys.push(arg_keys);
+
+            for binding in &provider.bindings {
+                add_use(
+                    &mut usage,
+                    &key,
+                    format!("`#[bind({})]`", display_type(binding)),
+                );
+                keys.push(normalize_type(binding, wrappers));
+            }
+        }
+        add_use(&mut usage, target_key, "the injector".to_string());
+
+        // A value borrowed mutably by one consumer while others use it would see
+        // changes depending on the order providers happen to be called in.
+        let mut shared: Vec<&String> = usage
+            .mutable
+            .iter()
+            .filter(|key| usage.uses[*key] > 1)
+            .collect();
+        shared.sort();
+        if let Some(key) = shared.first() {
+            let used_by = match usage.consumers[*key].as_slice() {
+                [only] => format!("more than once by {}", only),
+                consumers => format!("by {}", join_consumers(consumers)),
+            };
+            return Err(format!(
+                "`{}` is borrowed mutably, which requires exclusive access, but it is used {}. \
+                 Only a single provider may depend on a type it takes as `&mut`.",
+                display_key(graph, key, wrappers),
+                used_by
+            ));
+        }
+
+        Ok(usage)
+    }
+}
+
+fn join_consumers(consumers: &[String]) -> String {
+    match consumers {
+        [] => String::new(),
+        [only] => only.clone(),
+        [rest @ .., last] => format!("{} and {}", rest.join(", "), last),
+    }
+}
+
+fn clone_trait_name(key: &str) -> Ident {
+    format_ident!("WireClone_{}", ident_part(key))
+}
+
+/// Makes a normalized type usable in an identifier; keys of trait objects with
+/// auto traits (`repo+send`) or arrays (`[u8;4]`) contain other characters.
+fn ident_part(key: &str) -> String {
+    key.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// A local trait that clones the value of `key`, whose diagnostic explains why
+/// `Clone` is required when it is not implemented.
+fn clone_trait(key: &str, consumers: &[String]) -> TokenStream {
+    let name = clone_trait_name(key);
+    let message = format!(
+        "`{{Self}}` must implement `Clone` to be used by {}",
+        join_consumers(consumers)
+    );
+    let note = "wire moves a value into its last consumer and clones it for the others, \
+                and clones values out of smart pointers; take `&{Self}` instead, or implement `Clone`";
+    quote! {
+        #[diagnostic::on_unimplemented(message = #message, label = "cloned here", note = #note)]
+        #[allow(non_camel_case_types)]
+        trait #name {
+            fn wire_clone(&self) -> Self;
+        }
+        impl<T: ::core::clone::Clone> #name for T {
+            fn wire_clone(&self) -> Self {
+                ::core::clone::Clone::clone(self)
+            }
+        }
+    }
+}
+
+/// The type name shown for a node in a dependency chain: the provider's return
+/// type, or the `#[bind]` type the node was registered under.
+fn display_key(graph: &Graph, key: &str, wrappers: &[String]) -> String {
+    let provider = &graph.nodes[key].provider;
+    let ty = std::iter::once(&provider.ret)
+        .chain(&provider.bindings)
+        .find(|ty| normalize_type(ty, wrappers) == key)
+        .unwrap_or(&provider.ret);
+    display_type(ty)
+}
+
+/// The function `#[provider]` expands to: the helper attributes `#[bind]`, `#[inject]`
+/// and `#[wire(...)]` are removed, as they are only read by `wire-build`, and
+/// destructured parameters get the name `wire-build` records for them (`arg<i>`) and
+/// are destructured at the top of the body instead.
+pub fn expand_provider(mut func: ItemFn) -> ItemFn {
+    func.attrs.retain(|attr| !attr.path().is_ident("bind"));
+    let mut destructures = Vec::new();
+    for (index, input) in func.sig.inputs.iter_mut().enumerate() {
+        if let FnArg::Typed(pat_type) = input {
+            pat_type
+                .attrs
+                .retain(|attr| !attr.path().is_ident("wire") && !attr.path().is_ident("inject"));
+            let name = argument_name(index, &pat_type.pat);
+            if !matches!(&*pat_type.pat, syn::Pat::Ident(pat_ident) if pat_ident.ident == name) {
+                let name = format_ident!("{}", name);
+                let pat = std::mem::replace(&mut *pat_type.pat, syn::parse_quote!(#name));
+                destructures.push(syn::parse_quote! { let #pat = #name; });
+            }
+        }
+    }
+    func.block.stmts.splice(0..0, destructures);
+    func
+}
+
+/// Defines the error type of `#[wire(error = ...)]`, which records the provider that
+/// failed and the chain of types from the injector's target down to the one it builds.
+pub fn error_type(vis: &Visibility, error: &Ident) -> TokenStream {
+    let doc = format!(
+        "Error returned when a provider fails, generated by `#[wire(error = {})]`.",
+        error
+    );
+    quote! {
+        #[doc = #doc]
+        #[derive(Debug)]
+        #vis struct #error {
+            /// Path of the provider that failed, e.g. `crate::db::connect`.
+            pub provider: &'static str,
+            /// Types from the injector's target to the one the provider builds, e.g. `["App", "Db"]`.
+            pub chain: &'static [&'static str],
+            /// The provider's error.
+            pub source: ::std::boxed::Box<dyn ::std::error::Error + ::core::marker::Send + ::core::marker::Sync + 'static>,
+        }
+
+        impl ::core::fmt::Display for #error {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                write!(f, "failed to build {} in `{}`", self.chain.join(" -> "), self.provider)
+            }
+        }
+
+        impl ::std::error::Error for #error {
+            fn source(&self) -> ::core::option::Option<&(dyn ::std::error::Error + 'static)> {
+                ::core::option::Option::Some(&*self.source)
+            }
+        }
+    }
+}
+
+/// Pretty-prints generated items with `prettyplease`, falling back to the raw
+/// token string when the tokens are not a valid file.
+pub fn pretty(tokens: &TokenStream) -> String {
+    match syn::parse2::<syn::File>(tokens.clone()) {
+        Ok(file) => prettyplease::unparse(&file),
+        Err(_) => tokens.to_string(),
+    }
+}
@@ -0,0 +1,315 @@
+//! Renders a [`Graph`] as Graphviz DOT, Mermaid or JSON for design reviews.
+
+use crate::graph::{normalize_type, Graph};
+use crate::types::display_type;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+/// Version of the JSON export schema. Bump it whenever a field changes meaning.
+pub const EXPORT_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GraphFormat {
+    Dot,
+    Mermaid,
+    Json,
+}
+
+impl GraphFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            GraphFormat::Dot => "dot",
+            GraphFormat::Mermaid => "mmd",
+            GraphFormat::Json => "json",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeKind {
+    /// The return type of a provider.
+    Provider,
+    /// A type registered through `#[bind(...)]`.
+    Binding,
+    /// A type that is required but has no provider.
+    Missing,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeKind {
+    /// A regular parameter dependency.
+    Dependency,
+    /// A parameter whose provider was overridden with `#[inject(...)]`.
+    Inject,
+    /// A provider's concrete type bound to an additional type.
+    Bind,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExportNode {
+    pub id: String,
+    pub ty: String,
+    pub kind: NodeKind,
+    pub provider: Option<String>,
+    pub is_result: bool,
+}
+
+/// An edge pointing from a dependency to the node that consumes it.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExportEdge {
+    pub from: String,
+    pub to: String,
+    pub kind: EdgeKind,
+    pub arg: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GraphExport {
+    pub schema_version: u32,
+    pub name: String,
+    pub target: Option<String>,
+    pub nodes: Vec<ExportNode>,
+    pub edges: Vec<ExportEdge>,
+}
+
+impl GraphExport {
+    /// Builds an export of the whole graph, or only of the part reachable from
+    /// `target` when one is given.
+    pub fn new(graph: &Graph, name: &str, target: Option<&str>, wrappers: &[String]) -> Self {
+        let target = target.map(|t| graph.lookup(t).unwrap_or(t).to_string());
+        let included: BTreeSet<String> = match &target {
+            Some(t) => graph.reachable(t),
+            None => graph.nodes.keys().cloned().collect(),
+        };
+
+        let mut nodes = BTreeMap::new();
+        let mut edges = Vec::new();
+
+        for key in &included {
+            let Some(node) = graph.nodes.get(key) else {
+                nodes.insert(
+                    key.clone(),
+                    ExportNode {
+                        id: key.clone(),
+                        ty: key.clone(),
+                        kind: NodeKind::Missing,
+                        provider: None,
+                        is_result: false,
+                    },
+                );
+                continue;
+            };
+            let p = &node.provider;
+            let primary = normalize_type(&p.ret, wrappers);
+
+            if *key != primary {
+                let ty = p
+                    .bindings
+                    .iter()
+                    .find(|b| normalize_type(b, wrappers) == *key)
+                    .map_or_else(|| key.clone(), |b| display_type(b));
+                nodes.insert(
+                    key.clone(),
+                    ExportNode {
+                        id: key.clone(),
+                        ty,
+                        kind: NodeKind::Binding,
+                        provider: Some(p.path.clone()),
+                        is_result: p.is_result,
+                    },
+                );
+                edges.push(ExportEdge {
+                    from: primary.clone(),
+                    to: key.clone(),
+                    kind: EdgeKind::Bind,
+                    arg: None,
+                });
+                // The concrete node carries the dependencies, make sure it is present.
+                if !included.contains(&primary) {
+                    nodes.insert(
+                        primary.clone(),
+                        ExportNode {
+                            id: primary.clone(),
+                            ty: display_type(&p.ret),
+                            kind: NodeKind::Provider,
+                            provider: Some(p.path.clone()),
+                            is_result: p.is_result,
+                        },
+                    );
+                } else {
+                    continue;
+                }
+            } else {
+                nodes.insert(
+                    key.clone(),
+                    ExportNode {
+                        id: key.clone(),
+                        ty: display_type(&p.ret),
+                        kind: NodeKind::Provider,
+                        provider: Some(p.path.clone()),
+                        is_result: p.is_result,
+                    },
+                );
+            }
+
+            for arg in &p.args {
+                let lookup_ty = normalize_type(&arg.lookup_type(), wrappers);
+                let dep = graph.lookup(&lookup_ty).unwrap_or(&lookup_ty).to_string();
+                if !graph.nodes.contains_key(&dep) {
+                    nodes.entry(dep.clone()).or_insert_with(|| ExportNode {
+                        id: dep.clone(),
+                        ty: display_type(&arg.lookup_type()),
+                        kind: NodeKind::Missing,
+                        provider: None,
+                        is_result: false,
+                    });
+                }
+                edges.push(ExportEdge {
+                    from: dep,
+                    to: primary.clone(),
+                    kind: if arg.from.is_some() {
+                        EdgeKind::Inject
+                    } else {
+                        EdgeKind::Dependency
+                    },
+                    arg: Some(arg.name.clone()),
+                });
+            }
+        }
+
+        edges.sort_by(|a, b| (&a.from, &a.to, &a.arg).cmp(&(&b.from, &b.to, &b.arg)));
+        edges.dedup_by(|a, b| a.from == b.from && a.to == b.to && a.arg == b.arg);
+
+        GraphExport {
+            schema_version: EXPORT_SCHEMA_VERSION,
+            name: name.to_string(),
+            target,
+            nodes: nodes.into_values().collect(),
+            edges,
+        }
+    }
+
+    pub fn render(&self, format: GraphFormat) -> String {
+        match format {
+            GraphFormat::Dot => self.to_dot(),
+            GraphFormat::Mermaid => self.to_mermaid(),
+            GraphFormat::Json => {
+                serde_json::to_string_pretty(self).expect("graph export is always serializable")
+            }
+        }
+    }
+
+    pub fn to_dot(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "digraph \"{}\" {{", dot_escape(&self.name));
+        let _ = writeln!(out, "    rankdir=LR;");
+        let _ = writeln!(out, "    node [shape=box, fontname=\"Helvetica\"];");
+        for node in &self.nodes {
+            let mut attrs = vec![format!("label=\"{}\"", node_label(node, "\\n", dot_escape))];
+            match node.kind {
+                NodeKind::Provider => {}
+                NodeKind::Binding => attrs.push("shape=ellipse, style=dashed".to_string()),
+                NodeKind::Missing => attrs.push("color=red, fontcolor=red".to_string()),
+            }
+            if node.is_result && node.kind == NodeKind::Provider {
+                attrs.push("style=filled, fillcolor=\"#fff3cd\"".to_string());
+            }
+            if self.target.as_deref() == Some(node.id.as_str()) {
+                attrs.push("peripheries=2".to_string());
+            }
+            let _ = writeln!(
+                out,
+                "    \"{}\" [{}];",
+                dot_escape(&node.id),
+                attrs.join(", ")
+            );
+        }
+        for edge in &self.edges {
+            let attrs = match edge.kind {
+                EdgeKind::Dependency => String::new(),
+                EdgeKind::Inject => " [label=\"#[inject]\", style=bold]".to_string(),
+                EdgeKind::Bind => " [label=\"bind\", style=dashed]".to_string(),
+            };
+            let _ = writeln!(
+                out,
+                "    \"{}\" -> \"{}\"{};",
+                dot_escape(&edge.from),
+                dot_escape(&edge.to),
+                attrs
+            );
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    pub fn to_mermaid(&self) -> String {
+        let ids: BTreeMap<&str, String> = self
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(i, n)| (n.id.as_str(), format!("n{}", i)))
+            .collect();
+
+        let mut out = String::from("flowchart LR\n");
+        for node in &self.nodes {
+            let id = &ids[node.id.as_str()];
+            let label = node_label(node, "<br/>", mermaid_escape);
+            let _ = match node.kind {
+                NodeKind::Binding => writeln!(out, "    {}([\"{}\"])", id, label),
+                _ => writeln!(out, "    {}[\"{}\"]", id, label),
+            };
+        }
+        for edge in &self.edges {
+            let (Some(from), Some(to)) = (ids.get(edge.from.as_str()), ids.get(edge.to.as_str()))
+            else {
+                continue;
+            };
+            let _ = match edge.kind {
+                EdgeKind::Dependency => writeln!(out, "    {} --> {}", from, to),
+                EdgeKind::Inject => writeln!(out, "    {} == \"#35;[inject]\" ==> {}", from, to),
+                EdgeKind::Bind => writeln!(out, "    {} -. bind .-> {}", from, to),
+            };
+        }
+        out.push_str("    classDef result fill:#fff3cd,stroke:#d39e00;\n");
+        out.push_str("    classDef missing stroke:#d00,color:#d00;\n");
+        out.push_str("    classDef target stroke-width:3px;\n");
+        for node in &self.nodes {
+            let id = &ids[node.id.as_str()];
+            if node.kind == NodeKind::Missing {
+                let _ = writeln!(out, "    class {} missing", id);
+            } else if node.is_result && node.kind == NodeKind::Provider {
+                let _ = writeln!(out, "    class {} result", id);
+            }
+            if self.target.as_deref() == Some(node.id.as_str()) {
+                let _ = writeln!(out, "    class {} target", id);
+            }
+        }
+        out
+    }
+}
+
+fn node_label(node: &ExportNode, line_break: &str, escape: fn(&str) -> String) -> String {
+    let mut lines = vec![escape(&node.ty)];
+    if let Some(provider) = &node.provider {
+        lines.push(escape(provider));
+    }
+    if node.kind == NodeKind::Missing {
+        lines.push("(missing provider)".to_string());
+    } else if node.is_result {
+        lines.push("(Result)".to_string());
+    }
+    lines.join(line_break)
+}
+
+fn dot_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn mermaid_escape(s: &str) -> String {
+    s.replace('"', "#quot;")
+        .replace('<', "#lt;")
+        .replace('>', "#gt;")
+}
@@ -0,0 +1,284 @@
+use crate::models::ProviderInfo;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub provider: ProviderInfo,
+}
+
+#[derive(Debug, Default)]
+pub struct Graph {
+    pub nodes: HashMap<String, Node>,
+    pub edges: HashMap<String, Vec<String>>,
+}
+
+pub fn is_match(full: &str, suffix: &str) -> bool {
+    full == suffix || full.ends_with(&format!("_{}", suffix))
+}
+
+impl Graph {
+    pub fn new(
+        providers: &[ProviderInfo],
+        wrappers: Vec<String>,
+    ) -> std::result::Result<Self, String> {
+        let mut graph = Graph::default();
+        let mut type_to_providers: HashMap<String, Vec<String>> = HashMap::new();
+
+        // Step 1: Check for duplicates
+        for p in providers {
+            let ty = normalize_type(&p.ret, &wrappers);
+            type_to_providers
+                .entry(ty)
+                .or_default()
+                .push(p.path.clone());
+            for b in &p.bindings {
+                let ty_b = normalize_type(b, &wrappers);
+                type_to_providers
+                    .entry(ty_b)
+                    .or_default()
+                    .push(p.path.clone());
+            }
+        }
+
+        let mut conflict_errors = Vec::new();
+        for (ty, paths) in type_to_providers.iter() {
+            if paths.len() > 1 {
+                conflict_errors.push(format!(
+                    "Multiple providers found for type '{}': {:?}",
+                    ty, paths
+                ));
+            }
+        }
+
+        if !conflict_errors.is_empty() {
+            return Err(conflict_errors.join("\n"));
+        }
+
+        // Step 2: Build the graph
+        for p in providers {
+            let ty = normalize_type(&p.ret, &wrappers);
+            let dependencies: Vec<String> = p
+                .args
+                .iter()
+                .map(|arg| normalize_type(&arg.lookup_type(), &wrappers))
+                .collect();
+
+            graph.nodes.insert(
+                ty.clone(),
+                Node {
+                    provider: p.clone(),
+                },
+            );
+            graph.edges.insert(ty, dependencies.clone());
+
+            for b in &p.bindings {
+                let ty_b = normalize_type(b, &wrappers);
+                graph.nodes.insert(
+                    ty_b.clone(),
+                    Node {
+                        provider: p.clone(),
+                    },
+                );
+                graph.edges.insert(ty_b, dependencies.clone());
+            }
+        }
+
+        Ok(graph)
+    }
+
+    /// Finds the node key for a normalized type, falling back to fuzzy matching
+    /// when the type was written with a different path prefix.
+    pub fn lookup(&self, ty: &str) -> Option<&str> {
+        if let Some((key, _)) = self.nodes.get_key_value(ty) {
+            return Some(key);
+        }
+        self.nodes
+            .keys()
+            .find(|k| is_match(ty, k) || is_match(k, ty))
+            .map(|k| k.as_str())
+    }
+
+    /// Returns the keys of `target` and every type it transitively depends on,
+    /// including types that have no provider.
+    pub fn reachable(&self, target: &str) -> BTreeSet<String> {
+        let mut seen = BTreeSet::new();
+        let mut stack = vec![target.to_string()];
+        while let Some(ty) = stack.pop() {
+            let key = self.lookup(&ty).unwrap_or(&ty).to_string();
+            if !seen.insert(key.clone()) {
+                continue;
+            }
+            if let Some(deps) = self.edges.get(&key) {
+                stack.extend(deps.iter().cloned());
+            }
+        }
+        seen
+    }
+
+    /// Returns the shortest chain of node keys from `from` to `to` along the
+    /// dependency edges, including both ends.
+    pub fn dependency_chain(&self, from: &str, to: &str) -> Option<Vec<String>> {
+        let mut parents: HashMap<String, String> = HashMap::new();
+        let mut queue = std::collections::VecDeque::from([from.to_string()]);
+        let mut seen = HashSet::from([from.to_string()]);
+        while let Some(key) = queue.pop_front() {
+            if key == to {
+                let mut chain = vec![key];
+                while let Some(parent) = parents.get(chain.last().unwrap()) {
+                    chain.push(parent.clone());
+                }
+                chain.reverse();
+                return Some(chain);
+            }
+            for dep in self.edges.get(&key).into_iter().flatten() {
+                let dep = self.lookup(dep).unwrap_or(dep).to_string();
+                if seen.insert(dep.clone()) {
+                    parents.insert(dep.clone(), key.clone());
+                    queue.push_back(dep);
+                }
+            }
+        }
+        None
+    }
+
+    pub fn resolve(&self, target_ty: &str) -> std::result::Result<Vec<ProviderInfo>, String> {
+        if self.nodes.is_empty() {
+            return Err("No providers found.".to_string());
+        }
+
+        if !self.nodes.contains_key(target_ty) {
+            let available: Vec<_> = self.nodes.keys().cloned().collect();
+            return Err(format!(
+                "Missing provider for type: {}. Available types: {:?}",
+                target_ty, available
+            ));
+        }
+
+        let mut sorted_providers = Vec::new();
+        let mut visiting = HashSet::new();
+        let mut visited = HashSet::new();
+
+        self.visit(
+            target_ty,
+            &mut visiting,
+            &mut visited,
+            &mut sorted_providers,
+        )?;
+
+        Ok(sorted_providers)
+    }
+
+    fn visit(
+        &self,
+        ty: &str,
+        visiting: &mut HashSet<String>,
+        visited: &mut HashSet<String>,
+        sorted_providers: &mut Vec<ProviderInfo>,
+    ) -> std::result::Result<(), String> {
+        let Some(ty) = self.lookup(ty) else {
+            let available: Vec<_> = self.nodes.keys().cloned().collect();
+            return Err(format!(
+                "Missing provider for type: {}. Available types: {:?}",
+                ty, available
+            ));
+        };
+        if visited.contains(ty) {
+            return Ok(());
+        }
+        if visiting.contains(ty) {
+            return Err(format!("Circular dependency detected on type: {}", ty));
+        }
+        let node = &self.nodes[ty];
+
+        visiting.insert(ty.to_string());
+        if let Some(dependencies) = self.edges.get(ty) {
+            for dep in dependencies {
+                self.visit(dep, visiting, visited, sorted_providers)?;
+            }
+        }
+        visiting.remove(ty);
+        visited.insert(ty.to_string());
+        // A provider is reached once for its return type and once for each `#[bind]`
+        // type that is depended on, but is only called once.
+        if !sorted_providers
+            .iter()
+            .any(|p| p.path == node.provider.path)
+        {
+            sorted_providers.push(node.provider.clone());
+        }
+
+        Ok(())
+    }
+}
+
+/// Checks that every provider in `providers` (as returned by [`Graph::resolve`])
+/// can be called from an injector in `module`, reporting the ones that cannot.
+pub fn check_visibility(
+    providers: &[ProviderInfo],
+    module: &str,
+) -> std::result::Result<(), String> {
+    let inaccessible: Vec<String> = providers
+        .iter()
+        .filter(|p| !p.is_visible_from(module))
+        .map(|p| format!("`{}` is only visible in `{}`", p.path, p.vis))
+        .collect();
+    if inaccessible.is_empty() {
+        return Ok(());
+    }
+    Err(format!(
+        "Providers are not accessible from `{}`: {}. Make them and their modules `pub(crate)`, or move the injector into their module.",
+        module,
+        inaccessible.join(", ")
+    ))
+}
+
+pub fn normalize_type(ty_str: &str, wrappers: &[String]) -> String {
+    // `&mut T` and `&'a mut T` depend on `T` just like `&T`.
+    let spaced = ty_str.replace('&', " & ");
+    let mut tokens: Vec<&str> = Vec::new();
+    for token in spaced.split_whitespace() {
+        let after_ref = tokens
+            .last()
+            .is_some_and(|prev| *prev == "&" || prev.starts_with('\''));
+        if !(token == "mut" && after_ref) {
+            tokens.push(token);
+        }
+    }
+    let mut s = tokens.concat().replace("&", "").replace("'", "");
+
+    // Recursive stripping of known wrappers
+    loop {
+        let mut changed = false;
+        for w in wrappers {
+            let search = format!("{}<", w);
+            if let Some(pos) = s.find(&search) {
+                if s.ends_with('>') {
+                    let prefix = &s[..pos];
+                    if prefix.is_empty() || prefix.ends_with("::") {
+                        s = s[pos + search.len()..s.len() - 1].to_string();
+                        changed = true;
+                        break;
+                    }
+                }
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    s = s
+        .replace("<", "_")
+        .replace(">", "_")
+        .replace("(", "_")
+        .replace(")", "_")
+        .replace(",", "_")
+        .replace("::", "_")
+        .to_lowercase();
+
+    if s.starts_with("dyn") {
+        s = s[3..].trim_start_matches('_').to_string();
+    }
+    s = s.replace("::dyn", "::");
+    s
+}
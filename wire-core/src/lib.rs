@@ -0,0 +1,10 @@
+//! Shared model, dependency graph and graph export used by both the `wire`
+//! macros and the `wire-build` scanner.
+
+pub mod attr;
+pub mod codegen;
+pub mod export;
+pub mod graph;
+pub mod manifest;
+pub mod models;
+pub mod types;
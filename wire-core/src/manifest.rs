@@ -0,0 +1,133 @@
+//! The versioned provider manifest written by `wire-build` and read by the `wire`
+//! macros, either as JSON or in a compact binary encoding.
+
+use crate::models::{InjectorInfo, ProviderInfo};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Version of the manifest schema. Bumped whenever [`ProviderManifest`] or the
+/// models it contains change in a way older readers cannot handle.
+pub const FORMAT_VERSION: u32 = 5;
+
+/// Leading bytes of a binary manifest, followed by the format version as a
+/// little-endian `u32` and the postcard-encoded [`ProviderManifest`].
+const BINARY_MAGIC: &[u8; 4] = b"WIRE";
+
+/// Encoding of a manifest file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ManifestFormat {
+    /// Human-readable JSON, for manifests that are inspected or consumed by other tools.
+    Json,
+    /// Compact postcard encoding, which is considerably faster to load.
+    Binary,
+}
+
+impl ManifestFormat {
+    /// [`ManifestFormat::Json`] for `.json` files, [`ManifestFormat::Binary`] otherwise.
+    pub fn for_path(path: &Path) -> Self {
+        if path.extension().is_some_and(|ext| ext == "json") {
+            ManifestFormat::Json
+        } else {
+            ManifestFormat::Binary
+        }
+    }
+}
+
+/// Top-level object of a providers manifest.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProviderManifest {
+    pub format_version: u32,
+    /// Name and version of the crate that wrote the manifest, e.g. `wire-build 0.1.0`.
+    pub producer: String,
+    pub providers: Vec<ProviderInfo>,
+    /// The `#[wire]` injectors of the crate, whose paths name the expansions written
+    /// for debugging. Empty in manifests exported to other crates.
+    pub injectors: Vec<InjectorInfo>,
+}
+
+impl ProviderManifest {
+    /// A manifest in the current format, without injectors.
+    pub fn new(producer: impl Into<String>, providers: Vec<ProviderInfo>) -> Self {
+        ProviderManifest {
+            format_version: FORMAT_VERSION,
+            producer: producer.into(),
+            providers,
+            injectors: Vec::new(),
+        }
+    }
+
+    pub fn to_bytes(&self, format: ManifestFormat) -> Vec<u8> {
+        match format {
+            ManifestFormat::Json => self.to_json().into_bytes(),
+            ManifestFormat::Binary => {
+                let mut bytes = BINARY_MAGIC.to_vec();
+                bytes.extend(self.format_version.to_le_bytes());
+                postcard::to_extend(self, bytes).expect("manifest serialization cannot fail")
+            }
+        }
+    }
+
+    /// Parses a manifest in either format, telling them apart by the leading bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let Some(rest) = bytes.strip_prefix(BINARY_MAGIC) else {
+            let json = std::str::from_utf8(bytes)
+                .map_err(|e| format!("Providers file is not valid UTF-8: {}", e))?;
+            return Self::from_json(json);
+        };
+
+        let version = rest
+            .get(..4)
+            .map(|v| u32::from_le_bytes(v.try_into().unwrap()));
+        if version != Some(FORMAT_VERSION) {
+            let found = version.map_or("none".to_string(), |v| v.to_string());
+            return Err(format!(
+                "Binary providers file format version {} does not match the expected version {}. \
+                 Use the same version of wire and wire-build.",
+                found, FORMAT_VERSION
+            ));
+        }
+        postcard::from_bytes(&rest[4..])
+            .map_err(|e| format!("Failed to decode binary providers file: {}", e))
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("manifest serialization cannot fail")
+    }
+
+    /// Parses a manifest, checking the format version before the contents so that
+    /// mismatched `wire` and `wire-build` versions are reported as such rather than
+    /// as a missing field.
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let value: serde_json::Value = serde_json::from_str(json)
+            .map_err(|e| format!("Providers file is not valid JSON: {}", e))?;
+
+        let Some(object) = value.as_object() else {
+            return Err(format!(
+                "Providers file has no format version and was written by an older wire-build; expected format version {}. \
+                 Use the same version of wire and wire-build.",
+                FORMAT_VERSION
+            ));
+        };
+        let version = object.get("format_version").and_then(|v| v.as_u64());
+        let producer = object
+            .get("producer")
+            .and_then(|v| v.as_str())
+            .unwrap_or("an unknown producer")
+            .to_string();
+        if version != Some(FORMAT_VERSION as u64) {
+            let found = version.map_or("none".to_string(), |v| v.to_string());
+            return Err(format!(
+                "Providers file format version {} (written by {}) does not match the expected version {}. \
+                 Use the same version of wire and wire-build.",
+                found, producer, FORMAT_VERSION
+            ));
+        }
+
+        serde_json::from_value(value).map_err(|e| {
+            format!(
+                "Failed to deserialize providers file written by {}: {}",
+                producer, e
+            )
+        })
+    }
+}
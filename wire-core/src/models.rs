@@ -0,0 +1,110 @@
+use crate::types::{rebase_crate, strip_option};
+use serde::{Deserialize, Serialize};
+
+// These structs are shared by `wire-build` (which produces them) and the `wire`
+// macros (which consume them from the provider manifest).
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProviderArgument {
+    pub name: String,
+    pub ty: String,
+    pub from: Option<String>,
+}
+
+impl ProviderArgument {
+    /// Whether the argument is an `Option`, which is `None` if its provider produces nothing.
+    pub fn is_optional(&self) -> bool {
+        strip_option(&self.ty).is_some()
+    }
+
+    /// The type the argument is resolved by: the `#[inject]` override, or its own
+    /// type without `Option`.
+    pub fn lookup_type(&self) -> String {
+        self.from
+            .clone()
+            .unwrap_or_else(|| strip_option(&self.ty).unwrap_or_else(|| self.ty.clone()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProviderInfo {
+    pub path: String,
+    /// Where the provider can be called from: [`PUBLIC`] if it is reachable from
+    /// other crates, otherwise the module it is visible in, e.g. `crate` for
+    /// `pub(crate)` or `crate::services` for a private function in `services`.
+    /// Takes the visibility of enclosing modules into account.
+    pub vis: String,
+    pub args: Vec<ProviderArgument>,
+    /// The provided type, without `Result` and `Option`.
+    pub ret: String,
+    pub is_result: bool,
+    /// Whether the provider returns an `Option` (or `Result<Option<_>, _>`) and may
+    /// produce nothing.
+    pub is_option: bool,
+    pub bindings: Vec<String>,
+    /// Expression from `#[provider(map_err = ...)]` applied to the provider's error
+    /// before it is propagated by the injector.
+    pub map_err: Option<String>,
+}
+
+/// [`ProviderInfo::vis`] of providers that are reachable from other crates.
+pub const PUBLIC: &str = "pub";
+
+impl ProviderInfo {
+    /// Whether the provider can be called from code in `module`, e.g. `crate::app`.
+    pub fn is_visible_from(&self, module: &str) -> bool {
+        self.vis == PUBLIC || module == self.vis || module.starts_with(&format!("{}::", self.vis))
+    }
+
+    /// The provider as used from a crate that depends on this one as `crate_name`:
+    /// `crate::` paths in its path, types, bindings and `map_err` refer to
+    /// `::crate_name::` instead. `None` if the provider is not a `crate::` item.
+    pub fn exported_as(&self, crate_name: &str) -> Option<ProviderInfo> {
+        let rest = self.path.strip_prefix("crate::")?;
+        let rebase = |tokens: &String| rebase_crate(tokens, crate_name);
+        Some(ProviderInfo {
+            path: format!("::{}::{}", crate_name, rest),
+            vis: self.vis.clone(),
+            args: self
+                .args
+                .iter()
+                .map(|arg| ProviderArgument {
+                    name: arg.name.clone(),
+                    ty: rebase(&arg.ty),
+                    from: arg.from.as_ref().map(rebase),
+                })
+                .collect(),
+            ret: rebase(&self.ret),
+            is_result: self.is_result,
+            is_option: self.is_option,
+            bindings: self.bindings.iter().map(rebase).collect(),
+            map_err: self.map_err.as_ref().map(rebase),
+        })
+    }
+}
+
+/// A function annotated with `#[wire]`, as found by the scanner.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InjectorInfo {
+    pub path: String,
+    pub vis: String,
+    pub sig: String,
+    pub ret: String,
+    pub is_result: bool,
+    pub is_option: bool,
+    pub wrappers: Vec<String>,
+    pub file: String,
+    /// Name of the error type generated for `#[wire(error = ...)]`.
+    pub error: Option<String>,
+    /// Line of the function's name, which tells injectors of the same name apart.
+    pub line: usize,
+}
+
+impl InjectorInfo {
+    /// The module the injector is defined in, e.g. `crate::app` for `crate::app::init`.
+    pub fn module(&self) -> &str {
+        self.path
+            .rsplit_once("::")
+            .map_or("crate", |(module, _)| module)
+    }
+}
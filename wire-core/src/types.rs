@@ -0,0 +1,136 @@
+use proc_macro2::{Group, Ident, Span, TokenStream, TokenTree};
+use quote::{quote, ToTokens};
+
+/// Splits a return type into its success type and whether it is a `Result`.
+///
+/// Both `Result<T, E>` and aliases such as `anyhow::Result<T>` are recognized by
+/// their last path segment.
+pub fn split_result(ty: &syn::Type) -> (String, bool) {
+    match result_ok(ty) {
+        Some(ok) => (ok.to_token_stream().to_string(), true),
+        None => (ty.to_token_stream().to_string(), false),
+    }
+}
+
+/// Like [`split_result`], but also unwraps an `Option` success type: returns the
+/// provided type, whether it is a `Result` and whether it is an `Option`, e.g.
+/// `(T, true, true)` for `Result<Option<T>, E>`.
+pub fn split_output(ty: &syn::Type) -> (String, bool, bool) {
+    let (ok, is_result) = match result_ok(ty) {
+        Some(ok) => (ok, true),
+        None => (ty, false),
+    };
+    match option_inner(ok) {
+        Some(inner) => (inner.to_token_stream().to_string(), is_result, true),
+        None => (ok.to_token_stream().to_string(), is_result, false),
+    }
+}
+
+/// The success type of a `Result`, or the type itself for a `Result` alias without
+/// type arguments. `None` if `ty` is not a `Result`.
+fn result_ok(ty: &syn::Type) -> Option<&syn::Type> {
+    let syn::Type::Path(type_path) = ty else {
+        return None;
+    };
+    let last = type_path.path.segments.last()?;
+    if last.ident != "Result" {
+        return None;
+    }
+    if let syn::PathArguments::AngleBracketed(args) = &last.arguments {
+        if let Some(syn::GenericArgument::Type(inner)) = args.args.first() {
+            return Some(inner);
+        }
+    }
+    // Assume Result but couldn't unwrap
+    Some(ty)
+}
+
+/// Returns the `T` of an `Option<T>`.
+pub fn option_inner(ty: &syn::Type) -> Option<&syn::Type> {
+    let syn::Type::Path(type_path) = ty else {
+        return None;
+    };
+    let last = type_path.path.segments.last()?;
+    if last.ident != "Option" {
+        return None;
+    }
+    let syn::PathArguments::AngleBracketed(args) = &last.arguments else {
+        return None;
+    };
+    match args.args.first() {
+        Some(syn::GenericArgument::Type(inner)) if args.args.len() == 1 => Some(inner),
+        _ => None,
+    }
+}
+
+/// [`option_inner`] for a type string as recorded by `wire-build`.
+pub fn strip_option(ty: &str) -> Option<String> {
+    let ty: syn::Type = syn::parse_str(ty).ok()?;
+    option_inner(&ty).map(|inner| inner.to_token_stream().to_string())
+}
+
+/// Renders a type string produced by `to_token_stream().to_string()` without
+/// the token spacing, e.g. `std :: sync :: Arc < Db >` becomes `std::sync::Arc<Db>`.
+pub fn display_type(ty: &str) -> String {
+    let mut out = String::with_capacity(ty.len());
+    let mut chars = ty.chars().peekable();
+    let mut prev: Option<char> = None;
+    while let Some(c) = chars.next() {
+        if c == ' ' {
+            let next = chars.peek().copied();
+            let glued = |ch: Option<char>| {
+                matches!(
+                    ch,
+                    Some(':' | '<' | '>' | '&' | ',' | '(' | ')' | '[' | ']' | '\'')
+                )
+            };
+            // Keep the space after a comma and around keywords like `dyn`.
+            if prev == Some(',') || !(glued(prev) || glued(next)) {
+                out.push(' ');
+            }
+            continue;
+        }
+        out.push(c);
+        prev = Some(c);
+    }
+    out
+}
+
+/// Name of the `index`th parameter of a provider, as recorded by `wire-build`.
+///
+/// Destructuring patterns such as `(a, b): (A, B)` are named `arg<index>`, which is
+/// also the name `#[provider]` gives them in the expanded function.
+pub fn argument_name(index: usize, pat: &syn::Pat) -> String {
+    match pat {
+        syn::Pat::Ident(pat_ident) if pat_ident.subpat.is_none() => pat_ident.ident.to_string(),
+        _ => format!("arg{}", index),
+    }
+}
+
+/// Replaces the `crate` keyword at the start of every path in a token string
+/// with `::<crate_name>`, e.g. `Arc < crate :: db :: Pool >` becomes
+/// `Arc < :: my_infra :: db :: Pool >`. Strings that are not valid tokens are
+/// returned unchanged.
+pub fn rebase_crate(tokens: &str, crate_name: &str) -> String {
+    match tokens.parse::<TokenStream>() {
+        Ok(stream) => rebase_stream(stream, crate_name).to_string(),
+        Err(_) => tokens.to_string(),
+    }
+}
+
+fn rebase_stream(stream: TokenStream, crate_name: &str) -> TokenStream {
+    let crate_ident = Ident::new(crate_name, Span::call_site());
+    stream
+        .into_iter()
+        .flat_map(|tree| match tree {
+            TokenTree::Ident(ident) if ident == "crate" => quote! { :: #crate_ident },
+            TokenTree::Group(group) => {
+                let mut rebased =
+                    Group::new(group.delimiter(), rebase_stream(group.stream(), crate_name));
+                rebased.set_span(group.span());
+                TokenTree::Group(rebased).into_token_stream()
+            }
+            tree => tree.into_token_stream(),
+        })
+        .collect()
+}
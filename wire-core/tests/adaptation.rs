@@ -0,0 +1,378 @@
+//! How injectors pass the value of a provider to arguments of another smart
+//! pointer or reference type.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use wire_core::attr::DEFAULT_WRAPPERS;
+use wire_core::codegen;
+use wire_core::models::{ProviderArgument, ProviderInfo};
+
+fn provider(path: &str, ret: &str, args: &[ProviderArgument]) -> ProviderInfo {
+    ProviderInfo {
+        path: path.to_string(),
+        vis: "pub".to_string(),
+        args: args.to_vec(),
+        ret: ret.to_string(),
+        is_result: false,
+        is_option: false,
+        bindings: Vec::new(),
+        map_err: None,
+    }
+}
+
+fn arg(ty: &str) -> ProviderArgument {
+    ProviderArgument {
+        name: "arg".to_string(),
+        ty: ty.to_string(),
+        from: None,
+    }
+}
+
+/// An argument with `#[inject(from)]`.
+fn inject(ty: &str, from: &str) -> ProviderArgument {
+    ProviderArgument {
+        from: Some(from.to_string()),
+        ..arg(ty)
+    }
+}
+
+/// Expands an injector returning `App`, built by `crate::app` from the `args`
+/// and the `providers` of their values.
+fn expand(providers: &[ProviderInfo], args: &[ProviderArgument]) -> String {
+    let wrappers: Vec<String> = DEFAULT_WRAPPERS.iter().map(|w| w.to_string()).collect();
+    expand_with(providers, args, &wrappers)
+}
+
+fn expand_with(
+    providers: &[ProviderInfo],
+    args: &[ProviderArgument],
+    wrappers: &[String],
+) -> String {
+    let mut providers = providers.to_vec();
+    providers.push(provider("crate::app", "App", args));
+    let sig: syn::Signature = syn::parse_str("fn init() -> App").unwrap();
+    codegen::expand(
+        &syn::Visibility::Inherited,
+        &sig,
+        wrappers,
+        None,
+        &providers,
+    )
+    .unwrap()
+    .to_string()
+}
+
+#[track_caller]
+fn assert_generates(expanded: &str, expected: TokenStream) {
+    let expected = expected.to_string();
+    assert!(
+        expanded.contains(&expected),
+        "expected `{}` in:\n{}",
+        expected,
+        expanded
+    );
+}
+
+#[test]
+fn arc_to_reference() {
+    let expanded = expand(&[provider("crate::db", "Arc<Db>", &[])], &[arg("&Db")]);
+    assert_generates(&expanded, quote! { crate::app(&*arc_0) });
+}
+
+#[test]
+fn arc_to_owned_clones_the_value() {
+    let expanded = expand(&[provider("crate::db", "Arc<Db>", &[])], &[arg("Db")]);
+    assert_generates(
+        &expanded,
+        quote! { crate::app(WireClone_db::wire_clone(&(*arc_0))) },
+    );
+}
+
+#[test]
+fn arc_to_arc_moves() {
+    let expanded = expand(&[provider("crate::db", "Arc<Db>", &[])], &[arg("Arc<Db>")]);
+    assert_generates(&expanded, quote! { crate::app(arc_0) });
+    assert!(!expanded.contains("wire_clone"));
+}
+
+#[test]
+fn arc_to_reference_to_arc() {
+    let expanded = expand(&[provider("crate::db", "Arc<Db>", &[])], &[arg("&Arc<Db>")]);
+    assert_generates(&expanded, quote! { crate::app(&arc_0) });
+}
+
+#[test]
+fn arc_to_arc_of_trait_object() {
+    let providers = [provider("crate::sql", "Arc<Sql>", &[])];
+    let expanded = expand(&providers, &[inject("Arc<dyn Repo>", "Arc<Sql>")]);
+    assert_generates(
+        &expanded,
+        quote! {
+            let arc_0_bridge_0: ::std::sync::Arc<dyn Repo> = arc_0 as ::std::sync::Arc<dyn Repo>;
+            let app_1 = crate::app(arc_0_bridge_0);
+        },
+    );
+}
+
+#[test]
+fn arc_to_reference_to_trait_object() {
+    let providers = [provider("crate::sql", "Arc<Sql>", &[])];
+    let expanded = expand(&providers, &[inject("&dyn Repo", "Arc<Sql>")]);
+    assert_generates(&expanded, quote! { crate::app(&*arc_0) });
+}
+
+#[test]
+fn arc_to_reference_to_arc_of_trait_object() {
+    let providers = [provider("crate::sql", "Arc<Sql>", &[])];
+    let expanded = expand(&providers, &[inject("&Arc<dyn Repo>", "Arc<Sql>")]);
+    assert_generates(
+        &expanded,
+        quote! {
+            let arc_0_bridge_0: ::std::sync::Arc<dyn Repo> = arc_0 as ::std::sync::Arc<dyn Repo>;
+            let app_1 = crate::app(&arc_0_bridge_0);
+        },
+    );
+}
+
+#[test]
+fn shared_arc_is_cloned_before_coercion() {
+    let providers = [provider("crate::sql", "Arc<Sql>", &[])];
+    let expanded = expand(
+        &providers,
+        &[inject("Arc<dyn Repo>", "Arc<Sql>"), arg("&Sql")],
+    );
+    assert_generates(
+        &expanded,
+        quote! {
+            let arc_0_bridge_0: ::std::sync::Arc<dyn Repo> =
+                WireClone_sql::wire_clone(&arc_0) as ::std::sync::Arc<dyn Repo>;
+            let app_1 = crate::app(arc_0_bridge_0, &*arc_0);
+        },
+    );
+}
+
+#[test]
+fn box_to_box_of_trait_object_moves() {
+    let providers = [provider("crate::sql", "Box<Sql>", &[])];
+    let expanded = expand(&providers, &[inject("Box<dyn Repo>", "Box<Sql>")]);
+    assert_generates(
+        &expanded,
+        quote! {
+            let box_0_bridge_0: ::std::boxed::Box<dyn Repo> = box_0 as ::std::boxed::Box<dyn Repo>;
+        },
+    );
+    assert!(!expanded.contains("wire_clone"));
+}
+
+#[test]
+fn box_to_owned_moves_out_of_the_box() {
+    let expanded = expand(&[provider("crate::db", "Box<Db>", &[])], &[arg("Db")]);
+    assert_generates(&expanded, quote! { crate::app(*box_0) });
+    assert!(!expanded.contains("wire_clone"));
+}
+
+#[test]
+fn box_to_mutable_reference() {
+    let expanded = expand(&[provider("crate::db", "Box<Db>", &[])], &[arg("&mut Db")]);
+    assert_generates(&expanded, quote! { let mut box_0 = crate::db(); });
+    assert_generates(&expanded, quote! { crate::app(&mut *box_0) });
+}
+
+#[test]
+fn box_to_arc_converts() {
+    let expanded = expand(&[provider("crate::db", "Box<Db>", &[])], &[arg("Arc<Db>")]);
+    assert_generates(
+        &expanded,
+        quote! {
+            let box_0_bridge_0: ::std::sync::Arc<Db> = ::std::sync::Arc::from(box_0);
+        },
+    );
+}
+
+#[test]
+fn rc_to_reference_owned_and_trait_object() {
+    let providers = [provider("crate::sql", "Rc<Sql>", &[])];
+    let expanded = expand(
+        &providers,
+        &[
+            arg("&Sql"),
+            arg("Sql"),
+            inject("Rc<dyn Repo>", "Rc<Sql>"),
+            inject("&dyn Repo", "Rc<Sql>"),
+        ],
+    );
+    assert_generates(
+        &expanded,
+        quote! {
+            let rc_0_bridge_2: ::std::rc::Rc<dyn Repo> = WireClone_sql::wire_clone(&rc_0) as ::std::rc::Rc<dyn Repo>;
+            let app_1 = crate::app(&*rc_0, WireClone_sql::wire_clone(&(*rc_0)), rc_0_bridge_2, &*rc_0);
+        },
+    );
+}
+
+#[test]
+fn owned_to_smart_pointers() {
+    let providers = [provider("crate::db", "Db", &[])];
+    assert_generates(
+        &expand(&providers, &[arg("Arc<Db>")]),
+        quote! {
+            let db_0_bridge_0: ::std::sync::Arc<Db> = ::std::sync::Arc::new(db_0);
+        },
+    );
+    assert_generates(
+        &expand(&providers, &[arg("Rc<Db>")]),
+        quote! {
+            let db_0_bridge_0: ::std::rc::Rc<Db> = ::std::rc::Rc::new(db_0);
+        },
+    );
+    assert_generates(
+        &expand(&providers, &[arg("&std::boxed::Box<Db>")]),
+        quote! {
+            let db_0_bridge_0: ::std::boxed::Box<Db> = ::std::boxed::Box::new(db_0);
+            let app_1 = crate::app(&db_0_bridge_0);
+        },
+    );
+}
+
+#[test]
+fn owned_to_arc_of_trait_object() {
+    let providers = [provider("crate::sql", "Sql", &[])];
+    let expanded = expand(&providers, &[inject("Arc<dyn Repo>", "Sql")]);
+    assert_generates(
+        &expanded,
+        quote! {
+            let sql_0_bridge_0: ::std::sync::Arc<dyn Repo> = ::std::sync::Arc::new(sql_0);
+        },
+    );
+}
+
+#[test]
+fn owned_to_reference_to_trait_object() {
+    let providers = [provider("crate::sql", "Sql", &[])];
+    let expanded = expand(&providers, &[inject("&dyn Repo", "Sql")]);
+    assert_generates(&expanded, quote! { crate::app(&sql_0) });
+}
+
+#[test]
+fn differently_qualified_pointers_are_the_same_type() {
+    let providers = [provider("crate::db", "std::sync::Arc<Db>", &[])];
+    let expanded = expand(&providers, &[arg("Arc<Db>")]);
+    assert_generates(&expanded, quote! { crate::app(arc_0) });
+}
+
+#[test]
+fn custom_wrapper_keeps_its_path() {
+    let wrappers = vec!["Shared".to_string()];
+    let providers = [provider("crate::db", "Db", &[])];
+    let expanded = expand_with(&providers, &[arg("my::Shared<Db>")], &wrappers);
+    assert_generates(
+        &expanded,
+        quote! {
+            let db_0_bridge_0: my::Shared<Db> = my::Shared::new(db_0);
+        },
+    );
+}
+
+#[test]
+fn value_used_twice_in_one_call_is_cloned() {
+    let expanded = expand(
+        &[provider("crate::db", "Db", &[])],
+        &[arg("&Db"), arg("Db")],
+    );
+    assert_generates(
+        &expanded,
+        quote! { crate::app(&db_0, WireClone_db::wire_clone(&db_0)) },
+    );
+}
+
+#[test]
+fn optional_arc_to_optional_reference_and_trait_object() {
+    let providers = [ProviderInfo {
+        is_option: true,
+        ..provider("crate::sql", "Arc<Sql>", &[])
+    }];
+    let expanded = expand(
+        &providers,
+        &[
+            arg("Option<&Sql>"),
+            inject("Option<Arc<dyn Repo>>", "Arc<Sql>"),
+        ],
+    );
+    assert_generates(
+        &expanded,
+        quote! {
+            let arc_0_bridge_1: Option<::std::sync::Arc<dyn Repo> > = match &arc_0 {
+                Some(value) => Some(WireClone_sql::wire_clone(&(*value)) as ::std::sync::Arc<dyn Repo>),
+                None => None,
+            };
+            let app_1 = crate::app(
+                match &arc_0 { Some(value) => Some(&*(*value)), None => None },
+                arc_0_bridge_1
+            );
+        },
+    );
+}
+
+#[test]
+fn bound_provider_is_called_once_and_cast_to_the_binding() {
+    let providers = [ProviderInfo {
+        bindings: vec!["Arc<dyn Repo>".to_string()],
+        ..provider("crate::sql", "Arc<Sql>", &[])
+    }];
+    let expanded = expand(&providers, &[arg("&Sql"), arg("Arc<dyn Repo>")]);
+    assert_eq!(
+        expanded
+            .matches(&quote! { crate::sql() }.to_string())
+            .count(),
+        1,
+        "{}",
+        expanded
+    );
+    assert_generates(
+        &expanded,
+        quote! {
+            let arc_as_repo: Arc<dyn Repo> = WireClone_sql::wire_clone(&arc_0) as Arc<dyn Repo>;
+            let app_2 = crate::app(&*arc_0, arc_as_repo);
+        },
+    );
+}
+
+#[test]
+fn trait_object_with_auto_traits_is_cloned_and_bound() {
+    let providers = [ProviderInfo {
+        bindings: vec!["Arc<dyn Repo + Send + Sync>".to_string()],
+        ..provider("crate::sql", "Arc<Sql>", &[])
+    }];
+    let expanded = expand(
+        &providers,
+        &[
+            inject("Arc<dyn Store + Send + Sync>", "Arc<Sql>"),
+            inject("Arc<dyn Store + Send + Sync>", "Arc<Sql>"),
+            arg("Arc<dyn Repo + Send + Sync>"),
+        ],
+    );
+    assert_generates(
+        &expanded,
+        quote! {
+            let arc_as_repo_send_sync: Arc<dyn Repo + Send + Sync> =
+                WireClone_sql::wire_clone(&arc_0) as Arc<dyn Repo + Send + Sync>;
+            let arc_0_bridge_0: ::std::sync::Arc<dyn Store + Send + Sync> =
+                WireClone_sql::wire_clone(&arc_0) as ::std::sync::Arc<dyn Store + Send + Sync>;
+        },
+    );
+}
+
+#[test]
+fn array_used_twice_is_cloned() {
+    let expanded = expand(
+        &[provider("crate::key", "[u8; 4]", &[])],
+        &[arg("[u8; 4]"), arg("[u8; 4]")],
+    );
+    assert_generates(&expanded, quote! { trait WireClone__u8_4_ });
+    assert_generates(
+        &expanded,
+        quote! {
+            crate::app(WireClone__u8_4_::wire_clone(&u84_0), WireClone__u8_4_::wire_clone(&u84_0))
+        },
+    );
+}
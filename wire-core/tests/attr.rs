@@ -0,0 +1,172 @@
+//! Parsing of the `#[inject]`, `#[wire(from)]` and `#[bind]` helper attributes, and
+//! where malformed ones are reported.
+
+use quote::ToTokens;
+use wire_core::attr::{parse_bind_attr, parse_inject_attr};
+
+/// Parses the attributes of `source`, a single-line function, and returns those of the
+/// function followed by those of its parameters.
+fn attrs(source: &str) -> Vec<syn::Attribute> {
+    let func: syn::ItemFn = syn::parse_str(source).unwrap();
+    let params = func.sig.inputs.into_iter().flat_map(|input| match input {
+        syn::FnArg::Typed(pat_type) => pat_type.attrs,
+        syn::FnArg::Receiver(receiver) => receiver.attrs,
+    });
+    func.attrs.into_iter().chain(params).collect()
+}
+
+/// The message of `result`'s error and the text of `source` it points at.
+fn error<T>(source: &str, result: syn::Result<T>) -> (String, String) {
+    let Err(error) = result else {
+        panic!("expected an error for `{}`", source)
+    };
+    let span = error.span();
+    (
+        error.to_string(),
+        source[span.start().column..span.end().column].to_string(),
+    )
+}
+
+fn inject(source: &str) -> syn::Result<Option<String>> {
+    let attr = attrs(source).remove(0);
+    parse_inject_attr(&attr).map(|ty| ty.map(|ty| ty.to_token_stream().to_string()))
+}
+
+fn bind(source: &str) -> syn::Result<Option<String>> {
+    let attr = attrs(source).remove(0);
+    parse_bind_attr(&attr).map(|ty| ty.map(|ty| ty.to_token_stream().to_string()))
+}
+
+/// Asserts that `result` fails with a message starting with `message` at the text `at`.
+#[track_caller]
+fn assert_error<T>(source: &str, result: syn::Result<T>, message: &str, at: &str) {
+    let (actual, actual_at) = error(source, result);
+    assert!(
+        actual.starts_with(message),
+        "expected `{}`, got `{}`",
+        message,
+        actual
+    );
+    assert_eq!(actual_at, at, "{}", actual);
+}
+
+/// The start of syn's message for a missing or invalid type.
+const EXPECTED_TYPE: &str = "expected one of: `for`, parentheses, `fn`";
+
+#[test]
+fn inject_type() {
+    assert_eq!(
+        inject("fn f(#[inject(Arc<dyn Repo>)] repo: &dyn Repo) {}")
+            .unwrap()
+            .unwrap(),
+        "Arc < dyn Repo >"
+    );
+}
+
+#[test]
+fn wire_from_type() {
+    assert_eq!(
+        inject(r#"fn f(#[wire(from = "Arc<dyn Repo>")] repo: &dyn Repo) {}"#)
+            .unwrap()
+            .unwrap(),
+        "Arc < dyn Repo >"
+    );
+}
+
+#[test]
+fn bind_type() {
+    assert_eq!(
+        bind("#[bind(Arc<dyn Repo>)] fn f() -> Arc<Sql> { todo!() }")
+            .unwrap()
+            .unwrap(),
+        "Arc < dyn Repo >"
+    );
+}
+
+#[test]
+fn other_attributes_are_ignored() {
+    assert_eq!(
+        inject("fn f(#[allow(unused)] repo: &dyn Repo) {}").unwrap(),
+        None
+    );
+    assert_eq!(bind("#[inline] fn f() {}").unwrap(), None);
+}
+
+#[test]
+fn malformed_inject() {
+    let source = "fn f(#[inject(Arc<)] repo: &dyn Repo) {}";
+    assert_error(
+        source,
+        inject(source),
+        &format!("unexpected end of input, {}", EXPECTED_TYPE),
+        ")",
+    );
+    let source = "fn f(#[inject(Arc<Db>, Pool)] repo: &dyn Repo) {}";
+    assert_error(source, inject(source), "unexpected token", ",");
+    let source = r#"fn f(#[inject("Db")] repo: &dyn Repo) {}"#;
+    assert_error(source, inject(source), EXPECTED_TYPE, "\"Db\"");
+    let source = "fn f(#[inject] repo: &dyn Repo) {}";
+    assert_error(
+        source,
+        inject(source),
+        "expected attribute arguments in parentheses: #[inject(...)]",
+        "inject",
+    );
+}
+
+#[test]
+fn malformed_wire_from() {
+    let literal = "expected a string literal, e.g. `from = \"Arc<dyn Repo>\"`";
+    let source = "fn f(#[wire(from = Arc<Db>)] db: &Db) {}";
+    assert_error(source, inject(source), literal, "Arc<Db>");
+    let source = "fn f(#[wire(from = )] db: &Db) {}";
+    assert_error(source, inject(source), literal, "=");
+    let source = r#"fn f(#[wire(from = "Arc<")] db: &Db) {}"#;
+    assert_error(
+        source,
+        inject(source),
+        &format!("unexpected end of input, {}", EXPECTED_TYPE),
+        "\"Arc<\"",
+    );
+
+    let unknown = "unknown parameter option, expected `from = \"Type\"`";
+    let source = r#"fn f(#[wire(form = "Db")] db: &Db) {}"#;
+    assert_error(source, inject(source), unknown, "form");
+    let source = "fn f(#[wire(from)] db: &Db) {}";
+    assert_error(source, inject(source), unknown, "from");
+
+    let source = "fn f(#[wire()] db: &Db) {}";
+    assert_error(
+        source,
+        inject(source),
+        "expected `#[wire(from = \"Type\")]`",
+        "#[wire()]",
+    );
+    let source = "fn f(#[wire] db: &Db) {}";
+    assert_error(
+        source,
+        inject(source),
+        "expected attribute arguments in parentheses: #[wire(...)]",
+        "wire",
+    );
+}
+
+#[test]
+fn malformed_bind() {
+    let source = "#[bind(dyn)] fn f() -> Sql { Sql }";
+    assert_error(
+        source,
+        bind(source),
+        "unexpected end of input, expected identifier",
+        ")",
+    );
+    let source = "#[bind(Arc<dyn Repo>, Box<dyn Repo>)] fn f() -> Sql { Sql }";
+    assert_error(source, bind(source), "unexpected token", ",");
+    let source = "#[bind] fn f() -> Sql { Sql }";
+    assert_error(
+        source,
+        bind(source),
+        "expected attribute arguments in parentheses: #[bind(...)]",
+        "bind",
+    );
+}
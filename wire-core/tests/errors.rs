@@ -0,0 +1,310 @@
+//! How injectors returning a `Result` propagate the errors of their providers.
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use wire_core::attr::DEFAULT_WRAPPERS;
+use wire_core::codegen;
+use wire_core::models::{ProviderArgument, ProviderInfo};
+
+fn provider(path: &str, ret: &str, args: &[&str]) -> ProviderInfo {
+    ProviderInfo {
+        path: path.to_string(),
+        vis: "pub".to_string(),
+        args: args
+            .iter()
+            .enumerate()
+            .map(|(i, ty)| ProviderArgument {
+                name: format!("arg{}", i),
+                ty: ty.to_string(),
+                from: None,
+            })
+            .collect(),
+        ret: ret.to_string(),
+        is_result: false,
+        is_option: false,
+        bindings: Vec::new(),
+        map_err: None,
+    }
+}
+
+fn fallible(provider: ProviderInfo) -> ProviderInfo {
+    ProviderInfo {
+        is_result: true,
+        ..provider
+    }
+}
+
+/// `App` needs `Repo`, which needs the fallible `Db`.
+fn providers() -> Vec<ProviderInfo> {
+    vec![
+        fallible(provider("crate::db::connect", "Db", &[])),
+        provider("crate::repo", "Repo", &["&Db"]),
+        provider("crate::app", "App", &["Repo"]),
+    ]
+}
+
+fn expand(sig: &str, error: Option<&str>, providers: &[ProviderInfo]) -> Result<String, String> {
+    let wrappers: Vec<String> = DEFAULT_WRAPPERS.iter().map(|w| w.to_string()).collect();
+    let sig: syn::Signature = syn::parse_str(sig).unwrap();
+    let error = error.map(|error| format_ident!("{}", error));
+    codegen::expand(
+        &syn::Visibility::Inherited,
+        &sig,
+        &wrappers,
+        error.as_ref(),
+        providers,
+    )
+    .map(|tokens| tokens.to_string())
+}
+
+#[track_caller]
+fn assert_generates(expanded: &str, expected: TokenStream) {
+    let expected = expected.to_string();
+    assert!(
+        expanded.contains(&expected),
+        "expected `{}` in:\n{}",
+        expected,
+        expanded
+    );
+}
+
+#[test]
+fn wire_error_type_is_emitted_next_to_the_injector() {
+    let expanded = expand(
+        "fn init() -> Result<App, WireError>",
+        Some("WireError"),
+        &providers(),
+    )
+    .unwrap();
+    assert!(expanded.starts_with(&quote! { #[doc = "Error returned when a provider fails, generated by `#[wire(error = WireError)]`."] }.to_string()), "{}", expanded);
+    assert_generates(
+        &expanded,
+        quote! {
+            #[derive(Debug)]
+            struct WireError {
+                #[doc = r" Path of the provider that failed, e.g. `crate::db::connect`."]
+                pub provider: &'static str,
+                #[doc = r#" Types from the injector's target to the one the provider builds, e.g. `["App", "Db"]`."#]
+                pub chain: &'static [&'static str],
+                #[doc = r" The provider's error."]
+                pub source: ::std::boxed::Box<dyn ::std::error::Error + ::core::marker::Send + ::core::marker::Sync + 'static>,
+            }
+        },
+    );
+}
+
+#[test]
+fn wire_error_displays_the_chain_and_provider() {
+    let expanded = expand(
+        "fn init() -> Result<App, WireError>",
+        Some("WireError"),
+        &providers(),
+    )
+    .unwrap();
+    assert_generates(
+        &expanded,
+        quote! {
+            impl ::core::fmt::Display for WireError {
+                fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                    write!(f, "failed to build {} in `{}`", self.chain.join(" -> "), self.provider)
+                }
+            }
+        },
+    );
+}
+
+#[test]
+fn wire_error_source_is_the_provider_error() {
+    let expanded = expand(
+        "fn init() -> Result<App, WireError>",
+        Some("WireError"),
+        &providers(),
+    )
+    .unwrap();
+    assert_generates(
+        &expanded,
+        quote! {
+            impl ::std::error::Error for WireError {
+                fn source(&self) -> ::core::option::Option<&(dyn ::std::error::Error + 'static)> {
+                    ::core::option::Option::Some(&*self.source)
+                }
+            }
+        },
+    );
+}
+
+#[test]
+fn wire_error_records_the_chain_to_the_failed_provider() {
+    let expanded = expand(
+        "fn init() -> Result<App, WireError>",
+        Some("WireError"),
+        &providers(),
+    )
+    .unwrap();
+    assert_generates(
+        &expanded,
+        quote! {
+            let db_0 = crate::db::connect().map_err(|source| WireError {
+                provider: "crate::db::connect",
+                chain: &["App", "Repo", "Db"],
+                source: ::core::convert::Into::into(source),
+            })?;
+        },
+    );
+}
+
+#[test]
+fn wire_error_reports_a_missing_optional_value() {
+    let providers = [
+        ProviderInfo {
+            is_option: true,
+            ..provider("crate::db::connect", "Db", &[])
+        },
+        provider("crate::app", "App", &["Db"]),
+    ];
+    let expanded = expand(
+        "fn init() -> Result<App, WireError>",
+        Some("WireError"),
+        &providers,
+    )
+    .unwrap();
+    assert_generates(
+        &expanded,
+        quote! {
+            None => return Err(WireError {
+                provider: "crate::db::connect",
+                chain: &["App", "Db"],
+                source: ::core::convert::Into::into("crate::db::connect provided no Db"),
+            }),
+        },
+    );
+}
+
+#[test]
+fn errors_are_converted_into_the_injector_error() {
+    let expanded = expand("fn init() -> Result<App, AppError>", None, &providers()).unwrap();
+    assert_generates(
+        &expanded,
+        quote! {
+            let db_0 = __wire_convert_error_of_crate_db_connect(crate::db::connect())?;
+        },
+    );
+}
+
+#[test]
+fn conversion_is_checked_per_provider() {
+    let providers = [
+        fallible(provider("crate::db::connect", "Db", &[])),
+        fallible(provider("crate::app", "App", &["&Db"])),
+    ];
+    let expanded = expand("fn init() -> Result<App, AppError>", None, &providers).unwrap();
+    for check in [
+        "__wire_convert_error_of_crate_db_connect",
+        "__wire_convert_error_of_crate_app",
+    ] {
+        let check = format_ident!("{}", check);
+        assert_generates(
+            &expanded,
+            quote! {
+                #[allow(non_snake_case)]
+                fn #check<T, E>(result: ::core::result::Result<T, E>) -> ::core::result::Result<T, AppError>
+                where
+                    E: ::core::convert::Into<AppError>,
+                {
+                    result.map_err(::core::convert::Into::into)
+                }
+            },
+        );
+    }
+}
+
+#[test]
+fn injector_without_fallible_providers_has_no_conversion_checks() {
+    let providers = [provider("crate::app", "App", &[])];
+    let expanded = expand("fn init() -> Result<App, AppError>", None, &providers).unwrap();
+    assert!(
+        !expanded.contains("__wire_convert_error_of"),
+        "{}",
+        expanded
+    );
+    assert_generates(&expanded, quote! { Ok(app_0) });
+}
+
+#[test]
+fn anyhow_errors_get_context() {
+    for sig in [
+        "fn init() -> anyhow::Result<App>",
+        "fn init() -> Result<App, anyhow::Error>",
+    ] {
+        let expanded = expand(sig, None, &providers()).unwrap();
+        assert_generates(
+            &expanded,
+            quote! {
+                let db_0 = ::anyhow::Context::context(crate::db::connect(), "while building Db")?;
+            },
+        );
+    }
+}
+
+#[test]
+fn eyre_errors_get_context() {
+    for sig in [
+        "fn init() -> eyre::Result<App>",
+        "fn init() -> Result<App, eyre::Report>",
+    ] {
+        let expanded = expand(sig, None, &providers()).unwrap();
+        assert_generates(
+            &expanded,
+            quote! {
+                let db_0 = ::eyre::WrapErr::wrap_err(crate::db::connect(), "while building Db")?;
+            },
+        );
+    }
+    let expanded = expand("fn init() -> color_eyre::Result<App>", None, &providers()).unwrap();
+    assert_generates(
+        &expanded,
+        quote! {
+            let db_0 = ::color_eyre::eyre::WrapErr::wrap_err(crate::db::connect(), "while building Db")?;
+        },
+    );
+}
+
+#[test]
+fn alias_without_error_type_propagates() {
+    // Also `anyhow::Result` imported by name, as the imports of the injector are not known.
+    let expanded = expand("fn init() -> Result<App>", None, &providers()).unwrap();
+    assert_generates(&expanded, quote! { let db_0 = crate::db::connect()?; });
+    assert!(
+        !expanded.contains("__wire_convert_error_of"),
+        "{}",
+        expanded
+    );
+    assert!(!expanded.contains("context"), "{}", expanded);
+}
+
+#[test]
+fn map_err_is_applied_before_the_conversion() {
+    let providers = [
+        ProviderInfo {
+            map_err: Some("crate::errors::wrap".to_string()),
+            ..fallible(provider("crate::db::connect", "Db", &[]))
+        },
+        provider("crate::app", "App", &["&Db"]),
+    ];
+    let expanded = expand("fn init() -> Result<App, AppError>", None, &providers).unwrap();
+    assert_generates(
+        &expanded,
+        quote! {
+            let db_0 = __wire_convert_error_of_crate_db_connect(crate::db::connect().map_err(crate::errors::wrap))?;
+        },
+    );
+}
+
+#[test]
+fn fallible_provider_needs_a_result_injector() {
+    let error = expand("fn init() -> App", None, &providers()).unwrap_err();
+    assert_eq!(
+        error,
+        "Target function must return Result because some providers return Result."
+    );
+}
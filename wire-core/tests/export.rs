@@ -0,0 +1,190 @@
+//! Rendering of dependency graphs for design reviews.
+
+use wire_core::attr::DEFAULT_WRAPPERS;
+use wire_core::export::{GraphExport, GraphFormat};
+use wire_core::graph::Graph;
+use wire_core::models::{ProviderArgument, ProviderInfo};
+
+fn provider(path: &str, ret: &str, args: &[ProviderArgument]) -> ProviderInfo {
+    ProviderInfo {
+        path: path.to_string(),
+        vis: "pub".to_string(),
+        args: args.to_vec(),
+        ret: ret.to_string(),
+        is_result: false,
+        is_option: false,
+        bindings: Vec::new(),
+        map_err: None,
+    }
+}
+
+fn arg(name: &str, ty: &str) -> ProviderArgument {
+    ProviderArgument {
+        name: name.to_string(),
+        ty: ty.to_string(),
+        from: None,
+    }
+}
+
+fn wrappers() -> Vec<String> {
+    DEFAULT_WRAPPERS.iter().map(|w| w.to_string()).collect()
+}
+
+/// `App` built from a fallible `Db`, a `Config` and a `Repo` bound to `Sql`,
+/// next to an unrelated `Metrics` that needs a `Clock` nobody provides.
+fn export(target: Option<&str>) -> GraphExport {
+    let providers = vec![
+        provider("crate::config", "Config", &[]),
+        ProviderInfo {
+            is_result: true,
+            ..provider("crate::db", "Db", &[arg("config", "&Config")])
+        },
+        ProviderInfo {
+            bindings: vec!["Arc<dyn Repo>".to_string()],
+            ..provider("crate::sql", "Arc<Sql>", &[arg("db", "&Db")])
+        },
+        provider(
+            "crate::app",
+            "App",
+            &[
+                arg("config", "&Config"),
+                ProviderArgument {
+                    from: Some("Arc<Sql>".to_string()),
+                    ..arg("repo", "Arc<dyn Repo>")
+                },
+            ],
+        ),
+        provider("crate::metrics", "Metrics", &[arg("clock", "Clock")]),
+    ];
+    let graph = Graph::new(&providers, wrappers()).unwrap();
+    GraphExport::new(&graph, "crate::init", target, &wrappers())
+}
+
+#[test]
+fn dot_marks_results_bindings_injections_and_missing_providers() {
+    let dot = export(None).to_dot();
+    assert!(
+        dot.starts_with("digraph \"crate::init\" {\n    rankdir=LR;\n"),
+        "{}",
+        dot
+    );
+    for line in [
+        r#""app" [label="App\ncrate::app"];"#,
+        r#""clock" [label="Clock\n(missing provider)", color=red, fontcolor=red];"#,
+        r##""db" [label="Db\ncrate::db\n(Result)", style=filled, fillcolor="#fff3cd"];"##,
+        r#""repo" [label="Arc<dyn Repo>\ncrate::sql", shape=ellipse, style=dashed];"#,
+        r#""config" -> "db";"#,
+        r##""sql" -> "app" [label="#[inject]", style=bold];"##,
+        r#""sql" -> "repo" [label="bind", style=dashed];"#,
+    ] {
+        assert!(
+            dot.contains(&format!("    {}\n", line)),
+            "missing `{}` in:\n{}",
+            line,
+            dot
+        );
+    }
+    assert!(dot.ends_with("}\n"));
+}
+
+#[test]
+fn dot_escapes_quotes_and_backslashes() {
+    let mut export = export(Some("config"));
+    export.name = r#"crate::"init"\x"#.to_string();
+    export.nodes[0].ty = r#"Label<"a\b">"#.to_string();
+    let dot = export.to_dot();
+    assert!(
+        dot.starts_with(r#"digraph "crate::\"init\"\\x" {"#),
+        "{}",
+        dot
+    );
+    assert!(
+        dot.contains(r#"[label="Label<\"a\\b\">\ncrate::config", peripheries=2];"#),
+        "{}",
+        dot
+    );
+}
+
+#[test]
+fn mermaid_numbers_nodes_and_escapes_labels() {
+    let mermaid = export(None).to_mermaid();
+    // Nodes are numbered in the order of their keys: app, clock, config, db, metrics, repo, sql.
+    for line in [
+        "flowchart LR",
+        r#"    n0["App<br/>crate::app"]"#,
+        r#"    n5(["Arc#lt;dyn Repo#gt;<br/>crate::sql"])"#,
+        "    n2 --> n3",
+        r##"    n6 == "#35;[inject]" ==> n0"##,
+        "    n6 -. bind .-> n5",
+        "    class n1 missing",
+        "    class n3 result",
+    ] {
+        assert!(
+            mermaid.lines().any(|l| l == line),
+            "missing `{}` in:\n{}",
+            line,
+            mermaid
+        );
+    }
+    assert!(!mermaid.contains("class n0 target"));
+    assert!(!mermaid.contains("Arc<"), "{}", mermaid);
+}
+
+#[test]
+fn target_limits_the_export_to_its_dependencies() {
+    let export = export(Some("app"));
+    let ids: Vec<&str> = export.nodes.iter().map(|n| n.id.as_str()).collect();
+    assert_eq!(ids, ["app", "config", "db", "sql"]);
+    assert!(export.to_mermaid().contains("    class n0 target\n"));
+    assert!(export
+        .to_dot()
+        .contains(r#""app" [label="App\ncrate::app", peripheries=2];"#));
+}
+
+#[test]
+fn json_shape() {
+    let json: serde_json::Value =
+        serde_json::from_str(&export(Some("app")).render(GraphFormat::Json)).unwrap();
+    assert_eq!(json["schema_version"], 1);
+    assert_eq!(json["name"], "crate::init");
+    assert_eq!(json["target"], "app");
+    assert_eq!(
+        json["nodes"][2],
+        serde_json::json!({ "id": "db", "ty": "Db", "kind": "provider", "provider": "crate::db", "is_result": true })
+    );
+    assert_eq!(
+        json["edges"],
+        serde_json::json!([
+            { "from": "config", "to": "app", "kind": "dependency", "arg": "config" },
+            { "from": "config", "to": "db", "kind": "dependency", "arg": "config" },
+            { "from": "db", "to": "sql", "kind": "dependency", "arg": "db" },
+            { "from": "sql", "to": "app", "kind": "inject", "arg": "repo" },
+        ])
+    );
+
+    let json: serde_json::Value =
+        serde_json::from_str(&export(None).render(GraphFormat::Json)).unwrap();
+    assert_eq!(json["target"], serde_json::Value::Null);
+    let kinds: Vec<&str> = json["nodes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|n| n["kind"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        kinds,
+        ["provider", "missing", "provider", "provider", "provider", "binding", "provider"]
+    );
+    assert!(json["edges"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|e| e["kind"] == "bind" && e["arg"].is_null()));
+}
+
+#[test]
+fn formats_have_their_extensions() {
+    assert_eq!(GraphFormat::Dot.extension(), "dot");
+    assert_eq!(GraphFormat::Mermaid.extension(), "mmd");
+    assert_eq!(GraphFormat::Json.extension(), "json");
+}
@@ -0,0 +1,84 @@
+//! Paths through the dependency graph, which diagnostics and `#[wire(error = ...)]` report.
+
+use wire_core::attr::DEFAULT_WRAPPERS;
+use wire_core::graph::Graph;
+use wire_core::models::{ProviderArgument, ProviderInfo};
+
+fn provider(path: &str, ret: &str, args: &[&str]) -> ProviderInfo {
+    ProviderInfo {
+        path: path.to_string(),
+        vis: "pub".to_string(),
+        args: args
+            .iter()
+            .enumerate()
+            .map(|(i, ty)| ProviderArgument {
+                name: format!("arg{}", i),
+                ty: ty.to_string(),
+                from: None,
+            })
+            .collect(),
+        ret: ret.to_string(),
+        is_result: false,
+        is_option: false,
+        bindings: Vec::new(),
+        map_err: None,
+    }
+}
+
+/// `App` reaches `Db` through `Repo` and `Cache`, and more directly through `Svc`.
+fn graph() -> Graph {
+    let providers = [
+        provider("crate::db", "Db", &[]),
+        provider("crate::cache", "Cache", &["&Db"]),
+        ProviderInfo {
+            bindings: vec!["Arc<dyn Repo>".to_string()],
+            ..provider("crate::sql", "Arc<Sql>", &["Cache"])
+        },
+        provider("crate::svc", "Svc", &["Arc<Db>"]),
+        provider("crate::app", "App", &["Arc<dyn Repo>", "Svc"]),
+        provider("crate::metrics", "Metrics", &["&App"]),
+    ];
+    Graph::new(
+        &providers,
+        DEFAULT_WRAPPERS.iter().map(|w| w.to_string()).collect(),
+    )
+    .unwrap()
+}
+
+#[test]
+fn chain_takes_the_shortest_path() {
+    assert_eq!(
+        graph().dependency_chain("app", "db").unwrap(),
+        ["app", "svc", "db"]
+    );
+}
+
+#[test]
+fn chain_goes_through_bindings() {
+    assert_eq!(
+        graph().dependency_chain("app", "cache").unwrap(),
+        ["app", "repo", "cache"]
+    );
+}
+
+#[test]
+fn chain_of_the_target_itself() {
+    assert_eq!(graph().dependency_chain("app", "app").unwrap(), ["app"]);
+}
+
+#[test]
+fn chain_to_an_unreachable_type() {
+    let graph = graph();
+    assert_eq!(graph.dependency_chain("app", "metrics"), None);
+    assert_eq!(graph.dependency_chain("db", "app"), None);
+    assert_eq!(graph.dependency_chain("app", "clock"), None);
+}
+
+#[test]
+fn chain_to_a_type_without_provider() {
+    let graph = Graph::new(&[provider("crate::app", "App", &["Clock"])], Vec::new()).unwrap();
+    assert_eq!(
+        graph.dependency_chain("app", "clock").unwrap(),
+        ["app", "clock"]
+    );
+}
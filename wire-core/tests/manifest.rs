@@ -0,0 +1,150 @@
+//! Encoding and decoding of the provider manifest shared by `wire-build` and `wire`.
+
+use std::path::Path;
+use wire_core::manifest::{ManifestFormat, ProviderManifest, FORMAT_VERSION};
+use wire_core::models::{ProviderArgument, ProviderInfo};
+
+fn manifest() -> ProviderManifest {
+    let db = ProviderInfo {
+        path: "crate::db::connect".to_string(),
+        vis: "pub".to_string(),
+        args: vec![ProviderArgument {
+            name: "config".to_string(),
+            ty: "& Config".to_string(),
+            from: Some("Arc < Config >".to_string()),
+        }],
+        ret: "Arc < Db >".to_string(),
+        is_result: true,
+        is_option: false,
+        bindings: vec!["Arc < dyn Repo >".to_string()],
+        map_err: Some("crate :: errors :: wrap".to_string()),
+    };
+    let config = ProviderInfo {
+        path: "crate::config::load".to_string(),
+        vis: "crate::config".to_string(),
+        args: Vec::new(),
+        ret: "Config".to_string(),
+        is_result: false,
+        is_option: true,
+        bindings: Vec::new(),
+        map_err: None,
+    };
+    ProviderManifest::new("wire-build 0.1.0", vec![db, config])
+}
+
+/// Compares through JSON, as the models do not implement `PartialEq`.
+#[track_caller]
+fn assert_same(decoded: &ProviderManifest, expected: &ProviderManifest) {
+    assert_eq!(decoded.to_json(), expected.to_json());
+}
+
+#[test]
+fn json_round_trip() {
+    let manifest = manifest();
+    let bytes = manifest.to_bytes(ManifestFormat::Json);
+    assert!(bytes.starts_with(b"{"));
+    assert_same(&ProviderManifest::from_bytes(&bytes).unwrap(), &manifest);
+    assert_same(
+        &ProviderManifest::from_json(&manifest.to_json()).unwrap(),
+        &manifest,
+    );
+}
+
+#[test]
+fn binary_round_trip() {
+    let manifest = manifest();
+    let bytes = manifest.to_bytes(ManifestFormat::Binary);
+    assert!(bytes.starts_with(b"WIRE"));
+    assert!(bytes.len() < manifest.to_bytes(ManifestFormat::Json).len());
+    let decoded = ProviderManifest::from_bytes(&bytes).unwrap();
+    assert_eq!(decoded.format_version, FORMAT_VERSION);
+    assert_eq!(decoded.producer, "wire-build 0.1.0");
+    assert_same(&decoded, &manifest);
+}
+
+#[test]
+fn format_follows_the_extension() {
+    assert_eq!(
+        ManifestFormat::for_path(Path::new("out/providers.json")),
+        ManifestFormat::Json
+    );
+    assert_eq!(
+        ManifestFormat::for_path(Path::new("out/providers.bin")),
+        ManifestFormat::Binary
+    );
+    assert_eq!(
+        ManifestFormat::for_path(Path::new("out/providers")),
+        ManifestFormat::Binary
+    );
+}
+
+#[test]
+fn json_of_another_version_is_rejected() {
+    let mut manifest = manifest();
+    manifest.format_version = FORMAT_VERSION + 1;
+    let error = ProviderManifest::from_json(&manifest.to_json()).unwrap_err();
+    assert!(
+        error.contains(&format!(
+            "format version {} (written by wire-build 0.1.0)",
+            FORMAT_VERSION + 1
+        )),
+        "{}",
+        error
+    );
+    assert!(
+        error.contains("Use the same version of wire and wire-build"),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn json_is_checked_for_the_version_before_the_contents() {
+    // A future format may have changed the providers in any way.
+    let json = format!(
+        r#"{{"format_version": {}, "producer": "wire-build 9.0.0", "providers": 1}}"#,
+        FORMAT_VERSION + 1
+    );
+    let error = ProviderManifest::from_json(&json).unwrap_err();
+    assert!(error.contains("written by wire-build 9.0.0"), "{}", error);
+
+    let error = ProviderManifest::from_json(r#"{"providers": []}"#).unwrap_err();
+    assert!(error.contains("format version none"), "{}", error);
+}
+
+#[test]
+fn unversioned_json_array_is_rejected() {
+    let error = ProviderManifest::from_json("[]").unwrap_err();
+    assert!(
+        error.contains("written by an older wire-build"),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn binary_of_another_version_is_rejected() {
+    let mut manifest = manifest();
+    manifest.format_version = FORMAT_VERSION + 1;
+    let error =
+        ProviderManifest::from_bytes(&manifest.to_bytes(ManifestFormat::Binary)).unwrap_err();
+    assert!(
+        error.contains(&format!("version {} does not match", FORMAT_VERSION + 1)),
+        "{}",
+        error
+    );
+
+    let error = ProviderManifest::from_bytes(b"WIRE").unwrap_err();
+    assert!(error.contains("version none"), "{}", error);
+}
+
+#[test]
+fn truncated_binary_is_rejected() {
+    let bytes = manifest().to_bytes(ManifestFormat::Binary);
+    let error = ProviderManifest::from_bytes(&bytes[..bytes.len() / 2]).unwrap_err();
+    assert!(
+        error.starts_with("Failed to decode binary providers file"),
+        "{}",
+        error
+    );
+}
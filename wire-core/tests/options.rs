@@ -0,0 +1,180 @@
+//! Injectors over providers that may produce nothing.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use wire_core::attr::DEFAULT_WRAPPERS;
+use wire_core::codegen;
+use wire_core::models::{ProviderArgument, ProviderInfo};
+
+fn provider(path: &str, ret: &str, args: &[&str]) -> ProviderInfo {
+    ProviderInfo {
+        path: path.to_string(),
+        vis: "pub".to_string(),
+        args: args
+            .iter()
+            .enumerate()
+            .map(|(i, ty)| ProviderArgument {
+                name: format!("arg{}", i),
+                ty: ty.to_string(),
+                from: None,
+            })
+            .collect(),
+        ret: ret.to_string(),
+        is_result: false,
+        is_option: false,
+        bindings: Vec::new(),
+        map_err: None,
+    }
+}
+
+fn optional(provider: ProviderInfo) -> ProviderInfo {
+    ProviderInfo {
+        is_option: true,
+        ..provider
+    }
+}
+
+fn fallible(provider: ProviderInfo) -> ProviderInfo {
+    ProviderInfo {
+        is_result: true,
+        ..provider
+    }
+}
+
+fn try_expand(sig: &str, providers: &[ProviderInfo]) -> Result<String, String> {
+    let wrappers: Vec<String> = DEFAULT_WRAPPERS.iter().map(|w| w.to_string()).collect();
+    let sig: syn::Signature = syn::parse_str(sig).unwrap();
+    codegen::expand(
+        &syn::Visibility::Inherited,
+        &sig,
+        &wrappers,
+        None,
+        providers,
+    )
+    .map(|tokens| tokens.to_string())
+}
+
+#[track_caller]
+fn assert_generates(expanded: &str, expected: TokenStream) {
+    let expected = expected.to_string();
+    assert!(
+        expanded.contains(&expected),
+        "expected `{}` in:\n{}",
+        expected,
+        expanded
+    );
+}
+
+#[test]
+fn missing_dependency_returns_none_from_an_option_injector() {
+    let providers = [
+        optional(provider("crate::cfg", "Cfg", &[])),
+        provider("crate::app", "App", &["Cfg"]),
+    ];
+    let expanded = try_expand("fn init() -> Option<App>", &providers).unwrap();
+    assert_generates(
+        &expanded,
+        quote! {
+            let cfg_0 = crate::cfg();
+            let cfg_0_some_0 = match cfg_0 {
+                Some(value) => value,
+                None => return None,
+            };
+            let app_1 = crate::app(cfg_0_some_0);
+            Some(app_1)
+        },
+    );
+}
+
+#[test]
+fn optional_target_is_returned_as_is() {
+    let providers = [optional(provider("crate::app", "App", &[]))];
+    let expanded = try_expand("fn init() -> Option<App>", &providers).unwrap();
+    assert_generates(&expanded, quote! { { let app_0 = crate::app(); app_0 } });
+}
+
+#[test]
+fn optional_dependency_of_a_plain_injector_is_rejected() {
+    let providers = [
+        optional(provider("crate::cfg", "Cfg", &[])),
+        provider("crate::app", "App", &["Cfg"]),
+    ];
+    let error = try_expand("fn init() -> App", &providers).unwrap_err();
+    assert_eq!(
+        error,
+        "`crate::cfg` may not provide `Cfg` because it returns an Option. Depend on `Option<&Cfg>` instead, \
+         make the injector return an Option, or use `#[wire(error = ...)]`."
+    );
+}
+
+#[test]
+fn optional_target_of_a_plain_injector_is_rejected() {
+    let providers = [optional(provider("crate::app", "App", &[]))];
+    let error = try_expand("fn init() -> App", &providers).unwrap_err();
+    assert!(
+        error.starts_with("`crate::app` may not provide `App` because it returns an Option."),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn option_argument_takes_the_value_or_none() {
+    let providers = [
+        optional(provider("crate::cfg", "Cfg", &[])),
+        provider("crate::app", "App", &["Option<&Cfg>"]),
+    ];
+    let expanded = try_expand("fn init() -> App", &providers).unwrap();
+    assert_generates(
+        &expanded,
+        quote! {
+            let app_1 = crate::app(match &cfg_0 { Some(value) => Some(&(*value)), None => None });
+            app_1
+        },
+    );
+}
+
+#[test]
+fn option_argument_of_a_present_value_is_some() {
+    let providers = [
+        provider("crate::cfg", "Cfg", &[]),
+        provider("crate::app", "App", &["Option<&Cfg>"]),
+    ];
+    let expanded = try_expand("fn init() -> App", &providers).unwrap();
+    assert_generates(&expanded, quote! { crate::app(Some(&cfg_0)) });
+}
+
+#[test]
+fn result_option_provider_propagates_errors_and_none() {
+    let providers = [
+        fallible(optional(provider("crate::cfg", "Cfg", &[]))),
+        provider("crate::app", "App", &["Cfg"]),
+    ];
+    let expanded = try_expand("fn init() -> Result<Option<App>, Error>", &providers).unwrap();
+    assert_generates(
+        &expanded,
+        quote! {
+            let cfg_0 = __wire_convert_error_of_crate_cfg(crate::cfg())?;
+            let cfg_0_some_0 = match cfg_0 {
+                Some(value) => value,
+                None => return Ok(None),
+            };
+            let app_1 = crate::app(cfg_0_some_0);
+            Ok(Some(app_1))
+        },
+    );
+}
+
+#[test]
+fn result_option_provider_needs_an_option_injector() {
+    let providers = [
+        fallible(optional(provider("crate::cfg", "Cfg", &[]))),
+        provider("crate::app", "App", &["Cfg"]),
+    ];
+    let error = try_expand("fn init() -> Result<App, Error>", &providers).unwrap_err();
+    assert!(
+        error.starts_with("`crate::cfg` may not provide `Cfg` because it returns an Option."),
+        "{}",
+        error
+    );
+}
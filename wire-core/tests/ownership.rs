@@ -0,0 +1,226 @@
+//! How injectors move, clone and mutably borrow the values of providers.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use wire_core::attr::DEFAULT_WRAPPERS;
+use wire_core::codegen;
+use wire_core::models::{ProviderArgument, ProviderInfo};
+
+fn provider(path: &str, ret: &str, args: &[&str]) -> ProviderInfo {
+    ProviderInfo {
+        path: path.to_string(),
+        vis: "pub".to_string(),
+        args: args
+            .iter()
+            .enumerate()
+            .map(|(i, ty)| ProviderArgument {
+                name: format!("arg{}", i),
+                ty: ty.to_string(),
+                from: None,
+            })
+            .collect(),
+        ret: ret.to_string(),
+        is_result: false,
+        is_option: false,
+        bindings: Vec::new(),
+        map_err: None,
+    }
+}
+
+/// Expands an injector returning `App`.
+fn try_expand(providers: &[ProviderInfo]) -> Result<String, String> {
+    let wrappers: Vec<String> = DEFAULT_WRAPPERS.iter().map(|w| w.to_string()).collect();
+    let sig: syn::Signature = syn::parse_str("fn init() -> App").unwrap();
+    codegen::expand(
+        &syn::Visibility::Inherited,
+        &sig,
+        &wrappers,
+        None,
+        providers,
+    )
+    .map(|tokens| tokens.to_string())
+}
+
+fn expand(providers: &[ProviderInfo]) -> String {
+    try_expand(providers).unwrap()
+}
+
+#[track_caller]
+fn assert_generates(expanded: &str, expected: TokenStream) {
+    let expected = expected.to_string();
+    assert!(
+        expanded.contains(&expected),
+        "expected `{}` in:\n{}",
+        expected,
+        expanded
+    );
+}
+
+#[test]
+fn value_is_moved_into_its_last_consumer() {
+    let expanded = expand(&[
+        provider("crate::db", "Db", &[]),
+        provider("crate::repo", "Repo", &["Db"]),
+        provider("crate::app", "App", &["Db", "Repo"]),
+    ]);
+    assert_generates(
+        &expanded,
+        quote! {
+            let db_0 = crate::db();
+            let repo_1 = crate::repo(WireClone_db::wire_clone(&db_0));
+            let app_2 = crate::app(db_0, repo_1);
+        },
+    );
+}
+
+#[test]
+fn references_do_not_count_as_moves() {
+    let expanded = expand(&[
+        provider("crate::db", "Db", &[]),
+        provider("crate::repo", "Repo", &["&Db"]),
+        provider("crate::app", "App", &["Db", "Repo"]),
+    ]);
+    assert_generates(&expanded, quote! { crate::repo(&db_0) });
+    assert_generates(&expanded, quote! { crate::app(db_0, repo_1) });
+    assert!(!expanded.contains("wire_clone"), "{}", expanded);
+}
+
+#[test]
+fn clone_diagnostic_names_each_consumer_once() {
+    let expanded = expand(&[
+        provider("crate::db", "Db", &[]),
+        provider("crate::repo", "Repo", &["Db"]),
+        provider("crate::app", "App", &["Db", "Db", "Repo"]),
+    ]);
+    assert_generates(
+        &expanded,
+        quote! {
+            #[diagnostic::on_unimplemented(
+                message = "`{Self}` must implement `Clone` to be used by `crate::repo` and `crate::app`",
+                label = "cloned here",
+                note = "wire moves a value into its last consumer and clones it for the others, and clones values out of smart pointers; take `&{Self}` instead, or implement `Clone`"
+            )]
+            #[allow(non_camel_case_types)]
+            trait WireClone_db {
+                fn wire_clone(&self) -> Self;
+            }
+            impl<T: ::core::clone::Clone> WireClone_db for T {
+                fn wire_clone(&self) -> Self {
+                    ::core::clone::Clone::clone(self)
+                }
+            }
+        },
+    );
+}
+
+#[test]
+fn uncloned_values_get_no_clone_check() {
+    let expanded = expand(&[
+        provider("crate::db", "Db", &[]),
+        provider("crate::app", "App", &["Db"]),
+    ]);
+    assert!(!expanded.contains("WireClone"), "{}", expanded);
+}
+
+#[test]
+fn exclusive_mutable_borrow() {
+    let expanded = expand(&[
+        provider("crate::db", "Db", &[]),
+        provider("crate::app", "App", &["&mut Db"]),
+    ]);
+    assert_generates(
+        &expanded,
+        quote! {
+            let mut db_0 = crate::db();
+            let app_1 = crate::app(&mut db_0);
+        },
+    );
+}
+
+#[test]
+fn shared_mutable_borrow_is_rejected() {
+    let error = try_expand(&[
+        provider("crate::db", "Db", &[]),
+        provider("crate::repo", "Repo", &["&mut Db"]),
+        provider("crate::app", "App", &["&Db", "Repo"]),
+    ])
+    .unwrap_err();
+    assert_eq!(
+        error,
+        "`Db` is borrowed mutably, which requires exclusive access, but it is used by `crate::repo` and `crate::app`. \
+         Only a single provider may depend on a type it takes as `&mut`."
+    );
+}
+
+#[test]
+fn mutable_borrow_next_to_another_use_in_one_call_is_rejected() {
+    let error = try_expand(&[
+        provider("crate::db", "Db", &[]),
+        provider("crate::app", "App", &["&mut Db", "&Db"]),
+    ])
+    .unwrap_err();
+    assert!(error.starts_with("`Db` is borrowed mutably, which requires exclusive access, but it is used more than once by `crate::app`."), "{}", error);
+}
+
+#[test]
+fn last_use_is_moved_into_a_bridge() {
+    let expanded = expand(&[
+        provider("crate::db", "Db", &[]),
+        provider("crate::repo", "Repo", &["Db"]),
+        provider("crate::app", "App", &["Arc<Db>", "Repo"]),
+    ]);
+    assert_generates(
+        &expanded,
+        quote! {
+            let db_0_bridge_0: ::std::sync::Arc<Db> = ::std::sync::Arc::new(db_0);
+        },
+    );
+    assert_generates(
+        &expanded,
+        quote! { crate::repo(WireClone_db::wire_clone(&db_0)) },
+    );
+}
+
+#[test]
+fn two_owned_uses_in_one_call_both_clone_into_a_bridge() {
+    let expanded = expand(&[
+        provider("crate::db", "Db", &[]),
+        provider("crate::app", "App", &["Db", "Box<Db>"]),
+    ]);
+    assert_generates(
+        &expanded,
+        quote! {
+            let db_0_bridge_1: ::std::boxed::Box<Db> = ::std::boxed::Box::new(WireClone_db::wire_clone(&db_0));
+        },
+    );
+    assert_generates(
+        &expanded,
+        quote! { crate::app(WireClone_db::wire_clone(&db_0), db_0_bridge_1) },
+    );
+}
+
+#[test]
+fn last_use_of_a_box_moves_out_of_it() {
+    let expanded = expand(&[
+        provider("crate::db", "Box<Db>", &[]),
+        provider("crate::repo", "Repo", &["Db"]),
+        provider("crate::app", "App", &["Db", "Repo"]),
+    ]);
+    assert_generates(
+        &expanded,
+        quote! { crate::repo(WireClone_db::wire_clone(&(*box_0))) },
+    );
+    assert_generates(&expanded, quote! { crate::app(*box_0, repo_1) });
+}
+
+#[test]
+fn arc_is_cloned_out_of_even_for_its_last_use() {
+    let expanded = expand(&[
+        provider("crate::db", "Arc<Db>", &[]),
+        provider("crate::app", "App", &["Db"]),
+    ]);
+    assert_generates(
+        &expanded,
+        quote! { crate::app(WireClone_db::wire_clone(&(*arc_0))) },
+    );
+}
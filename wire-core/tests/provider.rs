@@ -0,0 +1,182 @@
+//! What `#[provider]` rejects, where it reports it, and the function it expands to.
+
+use quote::{quote, ToTokens};
+use wire_core::attr::{validate_provider, ProviderAttr};
+use wire_core::codegen;
+
+/// Validates a provider, with its `#[provider(...)]` attribute if it has arguments, and
+/// returns each error with the text it points at.
+fn errors(source: &str) -> Vec<(String, String)> {
+    let mut func: syn::ItemFn = syn::parse_str(source).unwrap();
+    let attr: ProviderAttr = match func
+        .attrs
+        .iter()
+        .position(|attr| attr.path().is_ident("provider"))
+    {
+        Some(index) => func.attrs.remove(index).parse_args().unwrap(),
+        None => ProviderAttr::default(),
+    };
+    let Err(error) = validate_provider(&attr, &func) else {
+        return Vec::new();
+    };
+    error
+        .into_iter()
+        .map(|e| (e.to_string(), spanned(source, &e)))
+        .collect()
+}
+
+/// The text of `source`, a single line, that `error` is reported at.
+fn spanned(source: &str, error: &syn::Error) -> String {
+    let span = error.span();
+    assert_eq!(span.start().line, 1, "{}", source);
+    source[span.start().column..span.end().column].to_string()
+}
+
+#[track_caller]
+fn assert_error(source: &str, message: &str, at: &str) {
+    assert_eq!(errors(source), [(message.to_string(), at.to_string())]);
+}
+
+#[test]
+fn async_provider_is_rejected() {
+    assert_error(
+        "pub async fn db() -> Db { Db }",
+        "providers cannot be `async`, as injectors call them synchronously; provide the value the future resolves to",
+        "async",
+    );
+}
+
+#[test]
+fn generic_provider_is_rejected() {
+    let message = "providers cannot have type or const parameters, as injectors need the concrete type they provide";
+    assert_error(
+        "fn repo<R: Repo>(repo: R) -> Svc { todo!() }",
+        message,
+        "R: Repo",
+    );
+    assert_error(
+        "fn buf<const N: usize>() -> Buf { todo!() }",
+        message,
+        "const N: usize",
+    );
+}
+
+#[test]
+fn lifetime_parameters_are_allowed() {
+    assert_eq!(
+        errors("fn repo<'a>(db: &'a Db) -> Repo<'a> { todo!() }"),
+        []
+    );
+}
+
+#[test]
+fn receiver_is_rejected() {
+    let message =
+        "providers cannot take `self`; make this an associated function without a receiver";
+    assert_error("fn repo(&self) -> Repo { todo!() }", message, "&self");
+    assert_error(
+        "fn repo(mut self, db: Db) -> Repo { todo!() }",
+        message,
+        "mut self",
+    );
+}
+
+#[test]
+fn impl_trait_return_is_rejected() {
+    let message =
+        "providers cannot return `impl Trait`; return a concrete type or a `Box<dyn Trait>`";
+    assert_error("fn repo() -> impl Repo { Sql }", message, "impl Repo");
+    assert_error(
+        "fn repo() -> Result<impl Repo, Error> { Ok(Sql) }",
+        message,
+        "impl Repo",
+    );
+}
+
+#[test]
+fn impl_trait_parameter_is_rejected() {
+    let message =
+        "providers cannot take `impl Trait` parameters; use a concrete type or `&dyn Trait`";
+    assert_error(
+        "fn svc(repo: &impl Repo) -> Svc { todo!() }",
+        message,
+        "impl Repo",
+    );
+    assert_error(
+        "fn svc(repos: Vec<impl Repo>) -> Svc { todo!() }",
+        message,
+        "impl Repo",
+    );
+}
+
+#[test]
+fn unit_return_is_rejected() {
+    assert_error(
+        "fn init() {}",
+        "providers must return the type they provide, but this function returns `()`",
+        "init",
+    );
+    assert_error(
+        "fn init() -> () {}",
+        "providers must return the type they provide, not `()`",
+        "()",
+    );
+}
+
+#[test]
+fn map_err_requires_a_result() {
+    assert_eq!(
+        errors("#[provider(map_err = Error::Db)] fn db() -> Db { Db }"),
+        [(
+            "`map_err` requires the provider to return a `Result`".to_string(),
+            "Error::Db".to_string()
+        )]
+    );
+    assert_eq!(
+        errors("#[provider(map_err = Error::Db)] fn db() -> Result<Db, DbError> { Ok(Db) }"),
+        []
+    );
+}
+
+#[test]
+fn every_violation_is_reported() {
+    let errors = errors("async fn repo<T>(self, t: impl Into<T>) -> impl Repo { Sql }");
+    let at: Vec<&str> = errors.iter().map(|(_, at)| at.as_str()).collect();
+    assert_eq!(at, ["async", "T", "impl Repo", "self", "impl Into<T>"]);
+}
+
+#[test]
+fn destructured_parameters_are_renamed() {
+    let func: syn::ItemFn = syn::parse_str(
+        "fn app(config: Config, (db, cache): (Db, Cache), Wrapper(repo): Wrapper, _: Clock) -> App { App::new(db, repo) }",
+    )
+    .unwrap();
+    let expected = quote! {
+        fn app(config: Config, arg1: (Db, Cache), arg2: Wrapper, arg3: Clock) -> App {
+            let (db, cache) = arg1;
+            let Wrapper(repo) = arg2;
+            let _ = arg3;
+            App::new(db, repo)
+        }
+    };
+    assert_eq!(
+        codegen::expand_provider(func).to_token_stream().to_string(),
+        expected.to_string()
+    );
+}
+
+#[test]
+fn helper_attributes_are_removed() {
+    let func: syn::ItemFn = syn::parse_str(
+        r#"#[bind(Arc<dyn Repo>)] #[inline] fn repo(#[inject(Arc<Pool>)] pool: &Pool, #[wire(from = "Cfg")] #[allow(unused)] cfg: Config) -> Arc<Sql> { todo!() }"#,
+    )
+    .unwrap();
+    let expected = quote! {
+        #[inline]
+        fn repo(pool: &Pool, #[allow(unused)] cfg: Config) -> Arc<Sql> { todo!() }
+    };
+    assert_eq!(
+        codegen::expand_provider(func).to_token_stream().to_string(),
+        expected.to_string()
+    );
+}
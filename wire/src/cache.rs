@@ -0,0 +1,248 @@
+//! In-process cache of provider manifests and their graphs.
+//!
+//! The proc-macro library stays loaded for the whole compilation of a crate, so
+//! every `#[wire]` invocation after the first one reuses the parsed manifest and,
+//! for the same wrappers, the graph built from it. Entries are keyed by path and
+//! invalidated when the file's modification time or size changes, which keeps
+//! long-running hosts such as rust-analyzer up to date.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::SystemTime;
+use wire_core::graph::Graph;
+use wire_core::manifest::ProviderManifest;
+use wire_core::models::{InjectorInfo, ProviderInfo};
+
+pub(crate) enum LoadError {
+    /// The manifest could not be read, e.g. because the build script has not run.
+    Read(String),
+    /// The manifest is malformed, or its providers do not form a valid graph.
+    Invalid(String),
+}
+
+struct CachedManifest {
+    modified: SystemTime,
+    len: u64,
+    providers: Vec<ProviderInfo>,
+    injectors: Vec<InjectorInfo>,
+    graphs: HashMap<Vec<String>, Result<Arc<Graph>, String>>,
+}
+
+static MANIFESTS: LazyLock<Mutex<HashMap<PathBuf, CachedManifest>>> =
+    LazyLock::new(Default::default);
+
+/// Returns the graph of the providers in the manifest at `path`, built with `wrappers`.
+pub(crate) fn graph(path: &Path, wrappers: &[String]) -> Result<Arc<Graph>, LoadError> {
+    let metadata = fs::metadata(path).map_err(|e| {
+        LoadError::Read(format!(
+            "Failed to read providers file at {:?}: {}",
+            path, e
+        ))
+    })?;
+    let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+    let len = metadata.len();
+
+    let mut manifests = MANIFESTS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let stale = manifests
+        .get(path)
+        .is_none_or(|cached| cached.modified != modified || cached.len != len);
+    if stale {
+        let bytes = fs::read(path).map_err(|e| {
+            LoadError::Read(format!(
+                "Failed to read providers file at {:?}: {}",
+                path, e
+            ))
+        })?;
+        let manifest = ProviderManifest::from_bytes(&bytes).map_err(|e| {
+            LoadError::Invalid(format!(
+                "{} ({:?}, read by wire {})",
+                e,
+                path,
+                env!("CARGO_PKG_VERSION")
+            ))
+        })?;
+        let cached = CachedManifest {
+            modified,
+            len,
+            providers: manifest.providers,
+            injectors: manifest.injectors,
+            graphs: HashMap::new(),
+        };
+        manifests.insert(path.to_path_buf(), cached);
+    }
+
+    let cached = manifests.get_mut(path).expect("manifest was just cached");
+    let providers = &cached.providers;
+    cached
+        .graphs
+        .entry(wrappers.to_vec())
+        .or_insert_with(|| Graph::new(providers, wrappers.to_vec()).map(Arc::new))
+        .clone()
+        .map_err(LoadError::Invalid)
+}
+
+/// Returns the path of the injector `name` defined on `line`, e.g. `crate::app::init`,
+/// from the manifest at `path` that [`graph`] loaded. The line is only compared if
+/// several injectors share the name.
+pub(crate) fn injector_path(path: &Path, name: &str, line: usize) -> Option<String> {
+    let manifests = MANIFESTS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let named: Vec<&InjectorInfo> = manifests
+        .get(path)?
+        .injectors
+        .iter()
+        .filter(|injector| injector.path.rsplit("::").next() == Some(name))
+        .collect();
+    match named.as_slice() {
+        [injector] => Some(injector.path.clone()),
+        _ => named
+            .iter()
+            .find(|injector| injector.line == line)
+            .map(|injector| injector.path.clone()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::time::Duration;
+    use wire_core::manifest::ManifestFormat;
+
+    fn provider(path: &str, ret: &str) -> ProviderInfo {
+        ProviderInfo {
+            path: path.to_string(),
+            vis: "pub".to_string(),
+            args: Vec::new(),
+            ret: ret.to_string(),
+            is_result: false,
+            is_option: false,
+            bindings: Vec::new(),
+            map_err: None,
+        }
+    }
+
+    fn injector(path: &str, line: usize) -> InjectorInfo {
+        InjectorInfo {
+            path: path.to_string(),
+            vis: String::new(),
+            sig: String::new(),
+            ret: "App".to_string(),
+            is_result: false,
+            is_option: false,
+            wrappers: Vec::new(),
+            file: "providers.json".to_string(),
+            error: None,
+            line,
+        }
+    }
+
+    fn write(path: &Path, providers: Vec<ProviderInfo>) {
+        let manifest = ProviderManifest::new("test", providers);
+        fs::write(path, manifest.to_bytes(ManifestFormat::Binary)).unwrap();
+    }
+
+    fn load(path: &Path) -> Arc<Graph> {
+        match graph(path, &["Arc".to_string()]) {
+            Ok(graph) => graph,
+            Err(LoadError::Read(e) | LoadError::Invalid(e)) => panic!("{}", e),
+        }
+    }
+
+    fn provides(graph: &Graph, path: &str) -> bool {
+        graph.nodes.values().any(|node| node.provider.path == path)
+    }
+
+    #[test]
+    fn manifest_is_reloaded_when_it_changes() {
+        let dir = std::env::temp_dir().join(format!("wire-cache-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("providers.bin");
+
+        write(&path, vec![provider("crate::db", "Db")]);
+        let first = load(&path);
+        assert!(provides(&first, "crate::db"));
+        assert!(
+            Arc::ptr_eq(&first, &load(&path)),
+            "unchanged manifest is reloaded"
+        );
+        assert!(
+            !Arc::ptr_eq(&first, &graph(&path, &[]).ok().unwrap()),
+            "graphs are per wrappers"
+        );
+
+        // A different size.
+        write(
+            &path,
+            vec![
+                provider("crate::db", "Db"),
+                provider("crate::cache", "Cache"),
+            ],
+        );
+        let second = load(&path);
+        assert!(provides(&second, "crate::cache"));
+
+        // The same size, only told apart by the modification time.
+        write(
+            &path,
+            vec![
+                provider("crate::db", "Db"),
+                provider("crate::queue", "Queue"),
+            ],
+        );
+        let modified = fs::metadata(&path).unwrap().modified().unwrap();
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(modified + Duration::from_secs(1))
+            .unwrap();
+        let third = load(&path);
+        assert!(!provides(&third, "crate::cache"));
+        assert!(provides(&third, "crate::queue"));
+
+        fs::write(&path, "[]").unwrap();
+        assert!(matches!(graph(&path, &[]), Err(LoadError::Invalid(_))));
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(matches!(graph(&path, &[]), Err(LoadError::Read(_))));
+    }
+
+    #[test]
+    fn injector_paths_are_told_apart_by_line() {
+        let dir = std::env::temp_dir().join(format!("wire-cache-injectors-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("providers.bin");
+        let injectors = vec![
+            injector("crate::init", 3),
+            injector("crate::app::init", 12),
+            injector("crate::cli::run", 5),
+        ];
+        let manifest = ProviderManifest {
+            injectors,
+            ..ProviderManifest::new("test", Vec::new())
+        };
+        fs::write(&path, manifest.to_bytes(ManifestFormat::Binary)).unwrap();
+        assert!(graph(&path, &[]).is_ok());
+
+        assert_eq!(
+            injector_path(&path, "run", 1).as_deref(),
+            Some("crate::cli::run")
+        );
+        assert_eq!(
+            injector_path(&path, "init", 3).as_deref(),
+            Some("crate::init")
+        );
+        assert_eq!(
+            injector_path(&path, "init", 12).as_deref(),
+            Some("crate::app::init")
+        );
+        assert_eq!(injector_path(&path, "init", 7), None);
+        assert_eq!(injector_path(&path, "other", 3), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,43 @@
+//! `#[wire]` without the providers manifest, as when an IDE expands it before build
+//! scripts have run. This package has no build script, so `OUT_DIR` is not set here.
+
+// The fallback reports itself through a deprecation warning, which must be emitted.
+#[expect(deprecated)]
+mod injectors {
+    pub struct App;
+
+    #[wire::wire]
+    pub fn init() -> App {}
+
+    #[wire::wire(error = WireError)]
+    pub fn try_init() -> Result<App, WireError> {}
+}
+
+use injectors::*;
+use std::error::Error;
+
+#[test]
+#[should_panic(expected = "not yet implemented")]
+fn body_is_todo() {
+    let _: App = init();
+}
+
+#[test]
+#[should_panic(expected = "not yet implemented")]
+fn fallible_body_is_todo() {
+    let _ = try_init();
+}
+
+#[test]
+fn error_type_is_still_defined() {
+    let error = WireError {
+        provider: "crate::db::connect",
+        chain: &["App", "Repo", "Db"],
+        source: "connection refused".into(),
+    };
+    assert_eq!(
+        error.to_string(),
+        "failed to build App -> Repo -> Db in `crate::db::connect`"
+    );
+    assert_eq!(error.source().unwrap().to_string(), "connection refused");
+}